@@ -1,12 +1,35 @@
+use chrono::{DateTime, Utc};
 use serde::{Serialize, de::DeserializeOwned};
 use worker::{Cache, Response};
 
 use crate::error::ApiError;
 
+const CACHED_AT_HEADER: &str = "X-Cached-At";
+
 fn cache_url(key: &str) -> String {
     format!("https://cache.local/{}", urlencoding::encode(key))
 }
 
+/// # Errors
+///
+/// Returns an error when the Cache API lookup fails.
+pub async fn cached_at(key: &str) -> Result<Option<DateTime<Utc>>, ApiError> {
+    let cache = Cache::default();
+    let Some(response) = cache.get(cache_url(key), true).await? else {
+        return Ok(None);
+    };
+
+    let stamped = response
+        .headers()
+        .get(CACHED_AT_HEADER)?
+        .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+        .map(|value| value.with_timezone(&Utc));
+    Ok(stamped)
+}
+
+/// # Errors
+///
+/// Returns an error when the cache lookup fails or the entry is not valid JSON.
 pub async fn get_json<T>(key: &str) -> Result<Option<T>, ApiError>
 where
     T: DeserializeOwned,
@@ -23,6 +46,10 @@ where
     Ok(Some(parsed))
 }
 
+/// # Errors
+///
+/// Returns an error when the value cannot be serialized or the cache write
+/// fails.
 pub async fn put_json<T>(key: &str, value: &T, ttl_seconds: u32) -> Result<(), ApiError>
 where
     T: Serialize,
@@ -37,11 +64,17 @@ where
     response
         .headers_mut()
         .set("Content-Type", "application/json; charset=utf-8")?;
+    response
+        .headers_mut()
+        .set(CACHED_AT_HEADER, &Utc::now().to_rfc3339())?;
 
     cache.put(cache_url(key), response).await?;
     Ok(())
 }
 
+/// # Errors
+///
+/// Returns an error when the cache lookup fails.
 pub async fn get_bytes(key: &str) -> Result<Option<Vec<u8>>, ApiError> {
     let cache = Cache::default();
     let mut cached = cache.get(cache_url(key), true).await?;
@@ -54,6 +87,9 @@ pub async fn get_bytes(key: &str) -> Result<Option<Vec<u8>>, ApiError> {
     Ok(Some(payload))
 }
 
+/// # Errors
+///
+/// Returns an error when the response cannot be built or the cache write fails.
 pub async fn put_bytes(
     key: &str,
     bytes: &[u8],
@@ -66,6 +102,9 @@ pub async fn put_bytes(
         .headers_mut()
         .set("Cache-Control", &format!("public, max-age={ttl_seconds}"))?;
     response.headers_mut().set("Content-Type", content_type)?;
+    response
+        .headers_mut()
+        .set(CACHED_AT_HEADER, &Utc::now().to_rfc3339())?;
 
     cache.put(cache_url(key), response).await?;
     Ok(())
@@ -0,0 +1,124 @@
+use std::collections::BTreeSet;
+
+use crate::ics::parse_event_dates;
+use crate::models::{CompareEvent, CompareResponse, ShiftedEvent};
+
+pub const MIN_EVENT_SIMILARITY: f64 = 0.5;
+
+#[must_use]
+pub fn compare_calendars(
+    from_semester: i32,
+    from_rows: &[(String, String)],
+    to_semester: i32,
+    to_rows: &[(String, String)],
+) -> CompareResponse {
+    let from_keys = from_rows
+        .iter()
+        .map(|(_, event)| event_bigrams(event))
+        .collect::<Vec<_>>();
+    let to_keys = to_rows
+        .iter()
+        .map(|(_, event)| event_bigrams(event))
+        .collect::<Vec<_>>();
+
+    let mut candidates = Vec::new();
+    for (from_index, from_key) in from_keys.iter().enumerate() {
+        for (to_index, to_key) in to_keys.iter().enumerate() {
+            let similarity = jaccard(from_key, to_key);
+            if similarity >= MIN_EVENT_SIMILARITY {
+                candidates.push((similarity, from_index, to_index));
+            }
+        }
+    }
+    candidates.sort_by(|left, right| {
+        right
+            .0
+            .total_cmp(&left.0)
+            .then(left.1.cmp(&right.1))
+            .then(left.2.cmp(&right.2))
+    });
+
+    let mut from_matched = vec![false; from_rows.len()];
+    let mut to_matched = vec![false; to_rows.len()];
+    let mut pairs = Vec::new();
+    for (similarity, from_index, to_index) in candidates {
+        if from_matched[from_index] || to_matched[to_index] {
+            continue;
+        }
+        from_matched[from_index] = true;
+        to_matched[to_index] = true;
+        pairs.push((similarity, from_index, to_index));
+    }
+    pairs.sort_by_key(|(_, _, to_index)| *to_index);
+
+    let mut shifted = Vec::new();
+    let mut unchanged_count = 0;
+    for (similarity, from_index, to_index) in pairs {
+        let (from_date, from_event) = &from_rows[from_index];
+        let (to_date, to_event) = &to_rows[to_index];
+        if from_date == to_date {
+            unchanged_count += 1;
+            continue;
+        }
+        shifted.push(ShiftedEvent {
+            from_date: from_date.clone(),
+            to_date: to_date.clone(),
+            from_event: from_event.clone(),
+            to_event: to_event.clone(),
+            shift_days: shift_days(from_date, to_date, to_semester),
+            similarity: (similarity * 100.0).round() / 100.0,
+        });
+    }
+
+    let unmatched = |rows: &[(String, String)], matched: &[bool]| {
+        rows.iter()
+            .zip(matched)
+            .filter(|(_, matched)| !**matched)
+            .map(|((date, event), _)| CompareEvent {
+                date: date.clone(),
+                event: event.clone(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    CompareResponse {
+        from: from_semester,
+        to: to_semester,
+        shifted,
+        added: unmatched(to_rows, &to_matched),
+        removed: unmatched(from_rows, &from_matched),
+        unchanged_count,
+    }
+}
+
+fn shift_days(from_date: &str, to_date: &str, to_semester: i32) -> Option<i64> {
+    // Both dates are placed in the target academic year so the shift reflects
+    // the calendar move, not the year between the two semesters.
+    let (from_start, _) = parse_event_dates(from_date, to_semester)?;
+    let (to_start, _) = parse_event_dates(to_date, to_semester)?;
+    Some((to_start - from_start).num_days())
+}
+
+fn event_bigrams(event: &str) -> BTreeSet<(char, char)> {
+    let chars = event
+        .chars()
+        .filter(|ch| ch.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<_>>();
+    if chars.len() == 1 {
+        return BTreeSet::from([(chars[0], chars[0])]);
+    }
+    chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+fn jaccard(left: &BTreeSet<(char, char)>, right: &BTreeSet<(char, char)>) -> f64 {
+    let union = left.union(right).count();
+    if union == 0 {
+        return 0.0;
+    }
+    let intersection = left.intersection(right).count();
+    // Event lists are a few hundred rows, far below f64's exact integer range.
+    #[allow(clippy::cast_precision_loss)]
+    let ratio = intersection as f64 / union as f64;
+    ratio
+}
@@ -0,0 +1,126 @@
+use csv::{ReaderBuilder, WriterBuilder};
+use worker::KvStore;
+
+use crate::error::ApiError;
+use crate::kv_store;
+use crate::models::{CORRECTIONS_KEY_PREFIX, CorrectionAction, EventCorrection};
+
+#[must_use]
+pub fn corrections_key(semester: i32) -> String {
+    format!("{CORRECTIONS_KEY_PREFIX}{semester}")
+}
+
+/// # Errors
+///
+/// Returns an error when the KV read fails or the stored corrections are not
+/// valid JSON.
+pub async fn load_corrections(
+    kv: Option<&KvStore>,
+    semester: i32,
+) -> Result<Vec<EventCorrection>, ApiError> {
+    let corrections = kv_store::get_json::<Vec<EventCorrection>>(kv, &corrections_key(semester))
+        .await?
+        .unwrap_or_default();
+    Ok(corrections)
+}
+
+/// # Errors
+///
+/// Returns `ApiError::Validation` for an invalid patch, `ApiError::Internal`
+/// without `CAL_KV`, or an error when the KV read or write fails.
+pub async fn patch_corrections(
+    kv: Option<&KvStore>,
+    semester: i32,
+    patch: Vec<EventCorrection>,
+) -> Result<Vec<EventCorrection>, ApiError> {
+    kv_store::require(kv)?;
+    validate_corrections(&patch)?;
+    let existing = load_corrections(kv, semester).await?;
+    let merged = merge_corrections(existing, patch);
+    kv_store::put_json(kv, &corrections_key(semester), &merged).await?;
+    Ok(merged)
+}
+
+/// # Errors
+///
+/// Returns `ApiError::Validation` for the first invalid correction.
+pub fn validate_corrections(patch: &[EventCorrection]) -> Result<(), ApiError> {
+    for correction in patch {
+        if correction.date.trim().is_empty() || correction.event.trim().is_empty() {
+            return Err(ApiError::Validation(
+                "corrections must match a non-empty date and event".to_string(),
+            ));
+        }
+        if correction.action == CorrectionAction::Replace
+            && correction.new_date.is_none()
+            && correction.new_event.is_none()
+        {
+            return Err(ApiError::Validation(format!(
+                "replace correction for {} {} needs new_date or new_event",
+                correction.date, correction.event
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[must_use]
+pub fn merge_corrections(
+    mut existing: Vec<EventCorrection>,
+    patch: Vec<EventCorrection>,
+) -> Vec<EventCorrection> {
+    for correction in patch {
+        existing.retain(|current| !current.matches(&correction.date, &correction.event));
+        if correction.action != CorrectionAction::Clear {
+            existing.push(correction);
+        }
+    }
+    existing
+}
+
+/// # Errors
+///
+/// Returns an error when the CSV does not parse or cannot be written back.
+pub fn apply_corrections(csv: &str, corrections: &[EventCorrection]) -> Result<String, ApiError> {
+    if corrections.is_empty() {
+        return Ok(csv.to_string());
+    }
+
+    let mut reader = ReaderBuilder::new().from_reader(csv.as_bytes());
+    let headers = reader.headers()?.clone();
+    let (Some(date_index), Some(event_index)) = (
+        headers.iter().position(|header| header == "date"),
+        headers.iter().position(|header| header == "event"),
+    ) else {
+        return Ok(csv.to_string());
+    };
+
+    let mut writer = WriterBuilder::new().from_writer(Vec::<u8>::new());
+    writer.write_record(&headers)?;
+    for record in reader.records() {
+        let mut row = record?.iter().map(str::to_string).collect::<Vec<_>>();
+        let matched = corrections
+            .iter()
+            .find(|correction| correction.matches(&row[date_index], &row[event_index]));
+
+        match matched.map(|correction| (correction.action, correction)) {
+            Some((CorrectionAction::Suppress, _)) => continue,
+            Some((CorrectionAction::Replace, correction)) => {
+                if let Some(new_date) = &correction.new_date {
+                    row[date_index].clone_from(new_date);
+                }
+                if let Some(new_event) = &correction.new_event {
+                    row[event_index].clone_from(new_event);
+                }
+            }
+            Some((CorrectionAction::Clear, _)) | None => {}
+        }
+        writer.write_record(&row)?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|error| ApiError::Internal(error.to_string()))?;
+    String::from_utf8(bytes)
+        .map_err(|error| ApiError::Internal(format!("corrected csv is not valid UTF-8: {error}")))
+}
@@ -1,90 +1,699 @@
-use chihlee_cal_to_csv::{ExtractOptions, extract_pdf_bytes_to_csv_string};
-use url::Url;
-use worker::Fetch;
+use std::collections::{BTreeMap, BTreeSet};
+
+use chihlee_cal_to_csv::{
+    CancellationToken, ExtractError, ExtractOptions, ExtractWarning, ProgressSink,
+    SharedProgressSink, SharedWarningSink, extract_pdf_bytes_to_csv_string,
+};
+use chrono::{DateTime, Datelike, Utc};
+use futures_channel::oneshot;
+use futures_util::future::{self, Either};
+use worker::{Context, Delay, Env, KvStore};
 
 use crate::cache;
+use crate::corrections;
 use crate::error::ApiError;
-use crate::models::SemesterLink;
+use crate::fixtures;
+use crate::http_client::{HttpClient, RuntimeClient};
+use crate::ics;
+use crate::kv_store;
+use crate::models::{
+    CANARY_KEY_PREFIX, CanaryRecord, EXTRACTION_FAILURE_KEY_PREFIX, ExtractionFailure,
+    NOTES_KEY_PREFIX, PDF_OVERRIDE_KEY_PREFIX, SYNC_CURSOR_KEY, SYNC_SUCCESS_KEY_PREFIX,
+    SemesterLink, SemesterStatus, SyncSuccess,
+};
+use crate::revisions;
 use crate::source_scraper;
 
 pub const CSV_CACHE_TTL_SECONDS: u32 = 120 * 24 * 60 * 60;
-pub const CSV_CACHE_KEY_PREFIX: &str = "csv:semester:v1:";
+pub const CSV_CACHE_KEY_PREFIX: &str = "csv:semester:v2:";
+pub const DEFAULT_MIN_EXTRACTED_ROWS: usize = 20;
+pub const DEFAULT_MIN_DISTINCT_MONTHS: usize = 6;
+pub const DEFAULT_MAX_EXTRACTION_WARNINGS: usize = 25;
+pub const CANARY_CSV_KEY_PREFIX: &str = "csv:canary:v2:";
+pub const DEFAULT_CANARY_ROW_DELTA_PERCENT: u32 = 20;
+pub const DEFAULT_EXTRACTION_BUDGET_MS: u64 = 5_000;
+pub const MAX_PAGE_TEXT_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineConfig {
+    pub backfill_semesters: Option<usize>,
+    pub canary_row_delta_percent: u32,
+    pub min_extracted_rows: usize,
+    pub min_distinct_months: usize,
+    pub max_extraction_warnings: usize,
+    pub extraction_budget_ms: u64,
+    pub dev_fixtures: bool,
+    pub calendar_noise: Vec<String>,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            backfill_semesters: None,
+            canary_row_delta_percent: DEFAULT_CANARY_ROW_DELTA_PERCENT,
+            min_extracted_rows: DEFAULT_MIN_EXTRACTED_ROWS,
+            min_distinct_months: DEFAULT_MIN_DISTINCT_MONTHS,
+            max_extraction_warnings: DEFAULT_MAX_EXTRACTION_WARNINGS,
+            extraction_budget_ms: DEFAULT_EXTRACTION_BUDGET_MS,
+            dev_fixtures: false,
+            calendar_noise: Vec::new(),
+        }
+    }
+}
+
+impl PipelineConfig {
+    #[must_use]
+    pub fn from_env(env: &Env) -> Self {
+        let var = |name: &str| env.var(name).ok().map(|value| value.to_string());
+        let defaults = Self::default();
+
+        Self {
+            backfill_semesters: var("SYNC_BACKFILL_SEMESTERS")
+                .and_then(|value| parse_backfill_semesters(&value)),
+            canary_row_delta_percent: var("CANARY_ROW_DELTA_PERCENT")
+                .and_then(|value| value.trim().parse::<u32>().ok())
+                .unwrap_or(defaults.canary_row_delta_percent),
+            min_extracted_rows: var("MIN_EXTRACTED_ROWS")
+                .and_then(|value| value.trim().parse::<usize>().ok())
+                .unwrap_or(defaults.min_extracted_rows),
+            min_distinct_months: var("MIN_DISTINCT_MONTHS")
+                .and_then(|value| value.trim().parse::<usize>().ok())
+                .unwrap_or(defaults.min_distinct_months),
+            max_extraction_warnings: var("MAX_EXTRACTION_WARNINGS")
+                .and_then(|value| value.trim().parse::<usize>().ok())
+                .unwrap_or(defaults.max_extraction_warnings),
+            extraction_budget_ms: var("EXTRACTION_BUDGET_MS")
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .unwrap_or(defaults.extraction_budget_ms),
+            dev_fixtures: var("DEV_FIXTURES")
+                .is_some_and(|value| fixtures::parse_dev_fixtures(&value)),
+            calendar_noise: var("CALENDAR_NOISE")
+                .map(|value| parse_calendar_noise(&value))
+                .unwrap_or_default(),
+        }
+    }
+
+    #[must_use]
+    pub fn http_client(&self) -> RuntimeClient {
+        RuntimeClient::new(self.dev_fixtures)
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CsvCacheStatus {
     Hit,
     Miss,
     Bypass,
+    Stale,
+    Canary,
+    Degraded,
 }
 
 impl CsvCacheStatus {
+    #[must_use]
     pub const fn as_header_value(self) -> &'static str {
         match self {
             Self::Hit => "HIT",
             Self::Miss => "MISS",
             Self::Bypass => "BYPASS",
+            Self::Stale => "STALE",
+            Self::Canary => "CANARY",
+            Self::Degraded => "DEGRADED",
         }
     }
 }
 
+#[must_use]
 pub fn csv_cache_key(semester: i32) -> String {
     format!("{CSV_CACHE_KEY_PREFIX}{semester}")
 }
 
-pub async fn get_or_build_csv_for_link(link: &SemesterLink) -> Result<String, ApiError> {
-    let (csv, _) = get_or_build_csv_for_link_with_status(link).await?;
+#[must_use]
+pub fn pdf_override_key(semester: i32) -> String {
+    format!("{PDF_OVERRIDE_KEY_PREFIX}{semester}")
+}
+
+#[must_use]
+pub fn extraction_failure_key(semester: i32) -> String {
+    format!("{EXTRACTION_FAILURE_KEY_PREFIX}{semester}")
+}
+
+#[must_use]
+pub fn canary_key(semester: i32) -> String {
+    format!("{CANARY_KEY_PREFIX}{semester}")
+}
+
+#[must_use]
+pub fn canary_csv_cache_key(semester: i32) -> String {
+    format!("{CANARY_CSV_KEY_PREFIX}{semester}")
+}
+
+#[must_use]
+pub fn sync_success_key(semester: i32) -> String {
+    format!("{SYNC_SUCCESS_KEY_PREFIX}{semester}")
+}
+
+#[must_use]
+pub fn notes_key(semester: i32) -> String {
+    format!("{NOTES_KEY_PREFIX}{semester}")
+}
+
+struct BuiltCsv {
+    csv: String,
+    warning_count: usize,
+    notes: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum BudgetedCsv {
+    Ready(String, CsvCacheStatus),
+    Stale(String, BuildHandoff),
+    Building(BuildHandoff),
+}
+
+/// Held by the route until its response is ready. The build that passed the
+/// budget only resumes extraction once this is dropped, because that
+/// extraction would otherwise block the route's remaining I/O.
+#[derive(Debug)]
+pub struct BuildHandoff {
+    _release: oneshot::Sender<()>,
+}
+
+struct ExtractionBudget {
+    deadline: DateTime<Utc>,
+    exceeded: oneshot::Sender<()>,
+    released: oneshot::Receiver<()>,
+}
+
+/// # Errors
+///
+/// Returns an error when cache or KV access fails, or when a build that
+/// finishes within the budget fails.
+pub async fn build_csv_within_budget(
+    context: &Context,
+    kv: Option<&KvStore>,
+    link: &SemesterLink,
+    config: &PipelineConfig,
+    force: bool,
+) -> Result<BudgetedCsv, ApiError> {
+    let cached = get_cached_csv(link.semester).await?;
+    if !force && let Some(csv) = cached {
+        let csv = apply_corrections_overlay(kv, link.semester, &csv).await?;
+        return Ok(BudgetedCsv::Ready(csv, CsvCacheStatus::Hit));
+    }
+
+    if config.extraction_budget_ms == 0 {
+        let (csv, status) = if force {
+            rebuild_csv_for_link_with_status(kv, link, config).await?
+        } else {
+            get_or_build_csv_for_link_with_status(kv, link, config).await?
+        };
+        return Ok(BudgetedCsv::Ready(csv, status));
+    }
+
+    let budget = std::time::Duration::from_millis(config.extraction_budget_ms);
+    let deadline = Utc::now() + chrono::Duration::from_std(budget).unwrap_or(chrono::Duration::MAX);
+    let (sender, receiver) = oneshot::channel();
+    let (exceeded_sender, exceeded_receiver) = oneshot::channel();
+    let (release, released) = oneshot::channel();
+    let extraction_budget = ExtractionBudget {
+        deadline,
+        exceeded: exceeded_sender,
+        released,
+    };
+    let (task_kv, task_link, task_config) = (kv.cloned(), link.clone(), config.clone());
+    context.wait_until(async move {
+        let result = build_and_commit_csv(
+            task_kv.as_ref(),
+            &task_link,
+            &task_config,
+            force,
+            Some(extraction_budget),
+        )
+        .await;
+        if let Err(error) = &result {
+            worker::console_error!(
+                "background csv build failed for semester {}: {}",
+                task_link.semester,
+                error
+            );
+        }
+        let _ = sender.send(result);
+    });
+
+    // The timer only bounds the fetch: extraction runs synchronously, so
+    // passing the deadline mid-extraction is reported on `exceeded_receiver`.
+    // A dropped sender means the build finished within the budget.
+    let exceeded = async move {
+        if exceeded_receiver.await.is_err() {
+            future::pending::<()>().await;
+        }
+    };
+    let over_budget = future::select(Delay::from(budget), Box::pin(exceeded));
+    match future::select(receiver, over_budget).await {
+        Either::Left((Ok(result), _)) => {
+            let (csv, status) = result?;
+            Ok(BudgetedCsv::Ready(csv, status))
+        }
+        Either::Left((Err(_), _)) => Err(ApiError::Internal(
+            "background csv build was dropped".to_string(),
+        )),
+        Either::Right(_) => {
+            worker::console_warn!(
+                "csv build for semester {} exceeded {}ms budget; finishing in background",
+                link.semester,
+                config.extraction_budget_ms
+            );
+            let handoff = BuildHandoff { _release: release };
+            match cached {
+                Some(csv) => {
+                    let csv = apply_corrections_overlay(kv, link.semester, &csv).await?;
+                    Ok(BudgetedCsv::Stale(csv, handoff))
+                }
+                None => Ok(BudgetedCsv::Building(handoff)),
+            }
+        }
+    }
+}
+
+/// # Errors
+///
+/// Returns an error when the PDF cannot be fetched or converted, or when cache
+/// or KV access fails.
+pub async fn get_or_build_csv_for_link(
+    kv: Option<&KvStore>,
+    link: &SemesterLink,
+    config: &PipelineConfig,
+) -> Result<String, ApiError> {
+    let (csv, _) = get_or_build_csv_for_link_with_status(kv, link, config).await?;
     Ok(csv)
 }
 
+/// # Errors
+///
+/// Returns an error when the PDF cannot be fetched or converted, or when cache
+/// or KV access fails.
 pub async fn get_or_build_csv_for_link_with_status(
+    kv: Option<&KvStore>,
     link: &SemesterLink,
+    config: &PipelineConfig,
 ) -> Result<(String, CsvCacheStatus), ApiError> {
-    let cache_key = csv_cache_key(link.semester);
-    if let Some(cached) = cache::get_bytes(&cache_key).await? {
-        let csv = String::from_utf8(cached).map_err(|error| {
-            ApiError::Internal(format!("cached csv is not valid UTF-8: {error}"))
-        })?;
+    if let Some(csv) = get_cached_csv(link.semester).await? {
+        let csv = apply_corrections_overlay(kv, link.semester, &csv).await?;
         return Ok((csv, CsvCacheStatus::Hit));
     }
 
-    let csv = build_csv_from_pdf_url(&link.url).await?;
-    put_csv_in_cache(link.semester, &csv).await?;
-    Ok((csv, CsvCacheStatus::Miss))
+    build_and_commit_csv(kv, link, config, false, None).await
 }
 
-pub async fn rebuild_csv_for_link(link: &SemesterLink) -> Result<String, ApiError> {
-    let (csv, _) = rebuild_csv_for_link_with_status(link).await?;
+/// # Errors
+///
+/// Returns an error when the PDF cannot be fetched or converted, or when cache
+/// or KV access fails.
+pub async fn rebuild_csv_for_link(
+    kv: Option<&KvStore>,
+    link: &SemesterLink,
+    config: &PipelineConfig,
+) -> Result<String, ApiError> {
+    let (csv, _) = rebuild_csv_for_link_with_status(kv, link, config).await?;
     Ok(csv)
 }
 
+/// # Errors
+///
+/// Returns an error when the PDF cannot be fetched or converted, or when cache
+/// or KV access fails.
 pub async fn rebuild_csv_for_link_with_status(
+    kv: Option<&KvStore>,
     link: &SemesterLink,
+    config: &PipelineConfig,
 ) -> Result<(String, CsvCacheStatus), ApiError> {
-    let csv = build_csv_from_pdf_url(&link.url).await?;
-    put_csv_in_cache(link.semester, &csv).await?;
-    Ok((csv, CsvCacheStatus::Bypass))
+    build_and_commit_csv(kv, link, config, true, None).await
 }
 
-async fn put_csv_in_cache(semester: i32, csv: &str) -> Result<(), ApiError> {
+async fn build_and_commit_csv(
+    kv: Option<&KvStore>,
+    link: &SemesterLink,
+    config: &PipelineConfig,
+    force: bool,
+    budget: Option<ExtractionBudget>,
+) -> Result<(String, CsvCacheStatus), ApiError> {
+    let built = build_csv_for_link(kv, link, config, budget).await?;
+    let fresh_status = if force {
+        CsvCacheStatus::Bypass
+    } else {
+        CsvCacheStatus::Miss
+    };
+    let (csv, status) =
+        commit_built_csv(kv, link.semester, built, fresh_status, config, force).await?;
+    let csv = apply_corrections_overlay(kv, link.semester, &csv).await?;
+    Ok((csv, status))
+}
+
+async fn get_cached_csv(semester: i32) -> Result<Option<String>, ApiError> {
+    let Some(cached) = cache::get_bytes(&csv_cache_key(semester)).await? else {
+        return Ok(None);
+    };
+
+    let csv = String::from_utf8(cached)
+        .map_err(|error| ApiError::Internal(format!("cached csv is not valid UTF-8: {error}")))?;
+    Ok(Some(csv))
+}
+
+async fn commit_built_csv(
+    kv: Option<&KvStore>,
+    semester: i32,
+    built: BuiltCsv,
+    fresh_status: CsvCacheStatus,
+    config: &PipelineConfig,
+    hold_canary: bool,
+) -> Result<(String, CsvCacheStatus), ApiError> {
+    match validate_extraction(semester, &built.csv, built.warning_count, config) {
+        Ok(()) => {
+            // A held canary can only be promoted from its KV record.
+            if hold_canary
+                && kv.is_some()
+                && let Some(previous) = get_cached_csv(semester).await?
+            {
+                let cached_rows = count_csv_rows(&previous)?;
+                let canary_rows = count_csv_rows(&built.csv)?;
+                if exceeds_canary_threshold(
+                    cached_rows,
+                    canary_rows,
+                    config.canary_row_delta_percent,
+                ) {
+                    store_canary(kv, semester, &built.csv, cached_rows, canary_rows).await?;
+                    return Ok((previous, CsvCacheStatus::Canary));
+                }
+            }
+
+            put_built_csv(kv, semester, &built).await?;
+            kv_store::delete(kv, &canary_key(semester)).await?;
+            kv_store::delete(kv, &extraction_failure_key(semester)).await?;
+            record_sync_success(kv, semester, &built.csv).await?;
+            Ok((built.csv, fresh_status))
+        }
+        Err(error) => {
+            let previous = get_cached_csv(semester).await?;
+            // Without an earlier copy the rejected output is cached anyway, so
+            // the routes have something to serve; the flag stays set until a
+            // healthy rebuild replaces it.
+            let degraded_in_cache = match &previous {
+                Some(_) => {
+                    kv_store::get_json::<ExtractionFailure>(kv, &extraction_failure_key(semester))
+                        .await?
+                        .is_some_and(|failure| failure.degraded_in_cache)
+                }
+                None => true,
+            };
+            let failure = ExtractionFailure {
+                semester,
+                reason: error.message().to_string(),
+                observed_at: Utc::now().to_rfc3339(),
+                degraded_in_cache,
+            };
+            kv_store::put_json(kv, &extraction_failure_key(semester), &failure).await?;
+
+            if let Some(previous) = previous {
+                worker::console_error!("keeping cached csv for semester {}: {}", semester, error);
+                let status = if degraded_in_cache {
+                    CsvCacheStatus::Degraded
+                } else {
+                    CsvCacheStatus::Stale
+                };
+                return Ok((previous, status));
+            }
+
+            worker::console_error!(
+                "caching degraded csv for semester {} with no earlier copy: {}",
+                semester,
+                error
+            );
+            put_built_csv(kv, semester, &built).await?;
+            Ok((built.csv, CsvCacheStatus::Degraded))
+        }
+    }
+}
+
+async fn record_sync_success(
+    kv: Option<&KvStore>,
+    semester: i32,
+    csv: &str,
+) -> Result<(), ApiError> {
+    let record = SyncSuccess {
+        semester,
+        rows: count_csv_rows(csv)?,
+        synced_at: Utc::now().to_rfc3339(),
+    };
+    kv_store::put_json(kv, &sync_success_key(semester), &record).await
+}
+
+/// # Errors
+///
+/// Returns an error when the cache or KV read fails.
+pub async fn semester_status(
+    kv: Option<&KvStore>,
+    semester: i32,
+) -> Result<SemesterStatus, ApiError> {
+    let csv_cache_age_seconds = cache::cached_at(&csv_cache_key(semester))
+        .await?
+        .map(|cached_at| (Utc::now() - cached_at).num_seconds());
+
+    Ok(SemesterStatus {
+        semester,
+        last_success: kv_store::get_json(kv, &sync_success_key(semester)).await?,
+        last_failure: kv_store::get_json(kv, &extraction_failure_key(semester)).await?,
+        csv_cache_age_seconds,
+    })
+}
+
+async fn store_canary(
+    kv: Option<&KvStore>,
+    semester: i32,
+    csv: &str,
+    cached_rows: usize,
+    canary_rows: usize,
+) -> Result<(), ApiError> {
     cache::put_bytes(
-        &csv_cache_key(semester),
+        &canary_csv_cache_key(semester),
         csv.as_bytes(),
         CSV_CACHE_TTL_SECONDS,
         "text/csv; charset=utf-8",
     )
-    .await
+    .await?;
+
+    let record = CanaryRecord {
+        semester,
+        cached_rows,
+        canary_rows,
+        observed_at: Utc::now().to_rfc3339(),
+    };
+    kv_store::put_json(kv, &canary_key(semester), &record).await?;
+    worker::console_error!(
+        "holding csv for semester {} as canary: cached_rows={}, canary_rows={}",
+        semester,
+        cached_rows,
+        canary_rows
+    );
+    Ok(())
+}
+
+/// # Errors
+///
+/// Returns `ApiError::NotFound` when no canary is pending or its CSV expired,
+/// `ApiError::Internal` without `CAL_KV`, or an error when cache or KV access
+/// fails.
+pub async fn promote_canary(kv: Option<&KvStore>, semester: i32) -> Result<CanaryRecord, ApiError> {
+    kv_store::require(kv)?;
+    let record = kv_store::get_json::<CanaryRecord>(kv, &canary_key(semester))
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("no canary pending for semester {semester}")))?;
+    let csv = cache::get_bytes(&canary_csv_cache_key(semester))
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("canary csv for semester {semester} expired")))?;
+
+    cache::put_bytes(
+        &csv_cache_key(semester),
+        &csv,
+        CSV_CACHE_TTL_SECONDS,
+        "text/csv; charset=utf-8",
+    )
+    .await?;
+    kv_store::delete(kv, &canary_key(semester)).await?;
+    let csv = String::from_utf8(csv)
+        .map_err(|error| ApiError::Internal(format!("canary csv is not valid UTF-8: {error}")))?;
+    record_sync_success(kv, semester, &csv).await?;
+    Ok(record)
 }
 
-pub async fn sync_all_semesters(source_url: &str) -> Result<(), ApiError> {
-    let links = source_scraper::fetch_semester_links(source_url).await?;
+/// # Errors
+///
+/// Returns an error when a KV read fails.
+pub async fn list_canaries(kv: Option<&KvStore>) -> Result<Vec<CanaryRecord>, ApiError> {
+    let mut canaries = Vec::new();
+    for key in kv_store::list_keys(kv, CANARY_KEY_PREFIX).await? {
+        if let Some(record) = kv_store::get_json::<CanaryRecord>(kv, &key).await? {
+            canaries.push(record);
+        }
+    }
+    canaries.sort_by_key(|record| std::cmp::Reverse(record.semester));
+    Ok(canaries)
+}
+
+#[must_use]
+pub fn exceeds_canary_threshold(cached_rows: usize, canary_rows: usize, threshold: u32) -> bool {
+    if threshold == 0 || cached_rows == 0 {
+        return false;
+    }
+
+    let delta = cached_rows.abs_diff(canary_rows);
+    delta * 100 > cached_rows * usize::try_from(threshold).unwrap_or(usize::MAX)
+}
+
+fn count_csv_rows(csv: &str) -> Result<usize, ApiError> {
+    let mut reader = csv::ReaderBuilder::new().from_reader(csv.as_bytes());
+    let mut rows = 0;
+    for record in reader.records() {
+        record?;
+        rows += 1;
+    }
+    Ok(rows)
+}
+
+/// # Errors
+///
+/// Returns `ApiError::Validation` when the output breaks a configured
+/// threshold, or an error when the CSV does not parse.
+pub fn validate_extraction(
+    semester: i32,
+    csv: &str,
+    warning_count: usize,
+    config: &PipelineConfig,
+) -> Result<(), ApiError> {
+    if warning_count > config.max_extraction_warnings {
+        return Err(ApiError::Validation(format!(
+            "extraction produced {warning_count} warnings (max {})",
+            config.max_extraction_warnings
+        )));
+    }
+
+    let mut reader = csv::ReaderBuilder::new().from_reader(csv.as_bytes());
+    let date_index = reader
+        .headers()?
+        .iter()
+        .position(|header| header == "date")
+        .unwrap_or(0);
+    let mut dates = Vec::new();
+    for record in reader.records() {
+        if let Some(date) = record?.get(date_index) {
+            dates.push(date.to_string());
+        }
+    }
+    if dates.len() < config.min_extracted_rows {
+        return Err(ApiError::Validation(format!(
+            "extraction produced {} rows (min {})",
+            dates.len(),
+            config.min_extracted_rows
+        )));
+    }
+
+    // Dates are resolved the way `/ics` reads them, so year-prefixed rows
+    // such as `2025/9/15` count towards their own month.
+    let months = dates
+        .iter()
+        .filter_map(|date| ics::parse_event_dates(date, semester))
+        .map(|(start, _)| start.month())
+        .collect::<BTreeSet<_>>();
+    if months.len() < config.min_distinct_months {
+        return Err(ApiError::Validation(format!(
+            "extraction covers {} months (min {})",
+            months.len(),
+            config.min_distinct_months
+        )));
+    }
+
+    Ok(())
+}
+
+async fn apply_corrections_overlay(
+    kv: Option<&KvStore>,
+    semester: i32,
+    csv: &str,
+) -> Result<String, ApiError> {
+    let overlay = corrections::load_corrections(kv, semester).await?;
+    corrections::apply_corrections(csv, &overlay)
+}
+
+async fn put_built_csv(
+    kv: Option<&KvStore>,
+    semester: i32,
+    built: &BuiltCsv,
+) -> Result<(), ApiError> {
+    cache::put_bytes(
+        &csv_cache_key(semester),
+        built.csv.as_bytes(),
+        CSV_CACHE_TTL_SECONDS,
+        "text/csv; charset=utf-8",
+    )
+    .await?;
+    kv_store::put_json(kv, &notes_key(semester), &built.notes).await
+}
+
+async fn fetch_sync_links(
+    source_url: &str,
+    config: &PipelineConfig,
+) -> Result<Vec<SemesterLink>, ApiError> {
+    let mut links = source_scraper::fetch_semester_links(&config.http_client(), source_url).await?;
+    if let Some(limit) = config.backfill_semesters {
+        links.truncate(limit);
+    }
     if links.is_empty() {
         return Err(ApiError::NotFound(
             "no semester PDF links found from source page".to_string(),
         ));
     }
+    Ok(links)
+}
+
+/// # Errors
+///
+/// Returns an error when the source page has no links or the sync cursor cannot
+/// be read or written; a failed rebuild is only logged.
+pub async fn sync_next_semester(
+    source_url: &str,
+    kv: &KvStore,
+    config: &PipelineConfig,
+) -> Result<(), ApiError> {
+    let links = fetch_sync_links(source_url, config).await?;
+    let kv = Some(kv);
+    let cursor = kv_store::get_json::<u32>(kv, SYNC_CURSOR_KEY)
+        .await?
+        .unwrap_or(0);
+    let link = &links[next_sync_index(cursor, links.len())];
+    kv_store::put_json(kv, SYNC_CURSOR_KEY, &cursor.wrapping_add(1)).await?;
+
+    if let Err(error) = refresh_csv_for_link(kv, link, config).await {
+        worker::console_error!(
+            "csv sync failed for semester {} ({}): {}",
+            link.semester,
+            link.url,
+            error
+        );
+    }
 
-    for link in links {
-        if let Err(error) = refresh_csv_for_link(&link).await {
+    Ok(())
+}
+
+/// Without `CAL_KV` there is no round-robin cursor, so every run refreshes
+/// all semesters.
+///
+/// # Errors
+///
+/// Returns an error when the source page has no links; failed rebuilds are only
+/// logged.
+pub async fn sync_all_semesters(source_url: &str, config: &PipelineConfig) -> Result<(), ApiError> {
+    for link in fetch_sync_links(source_url, config).await? {
+        if let Err(error) = refresh_csv_for_link(None, &link, config).await {
             worker::console_error!(
                 "csv sync failed for semester {} ({}): {}",
                 link.semester,
@@ -97,53 +706,263 @@ pub async fn sync_all_semesters(source_url: &str) -> Result<(), ApiError> {
     Ok(())
 }
 
-async fn refresh_csv_for_link(link: &SemesterLink) -> Result<(), ApiError> {
-    let csv = build_csv_from_pdf_url(&link.url).await?;
-    put_csv_in_cache(link.semester, &csv).await
+#[must_use]
+pub fn parse_backfill_semesters(raw: &str) -> Option<usize> {
+    raw.trim().parse::<usize>().ok().filter(|limit| *limit > 0)
+}
+
+pub fn parse_calendar_noise(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|phrase| !phrase.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[must_use]
+pub fn next_sync_index(cursor: u32, link_count: usize) -> usize {
+    if link_count == 0 {
+        return 0;
+    }
+    usize::try_from(cursor).unwrap_or(usize::MAX) % link_count
 }
 
-async fn build_csv_from_pdf_url(pdf_url: &str) -> Result<String, ApiError> {
-    let pdf_bytes = fetch_pdf_bytes(pdf_url).await?;
-    convert_pdf_bytes_to_csv(&pdf_bytes)
+async fn refresh_csv_for_link(
+    kv: Option<&KvStore>,
+    link: &SemesterLink,
+    config: &PipelineConfig,
+) -> Result<(), ApiError> {
+    rebuild_csv_for_link_with_status(kv, link, config).await?;
+    Ok(())
 }
 
-async fn fetch_pdf_bytes(pdf_url: &str) -> Result<Vec<u8>, ApiError> {
-    let parsed = Url::parse(pdf_url)?;
-    let mut response = Fetch::Url(parsed).send().await?;
-    let status = response.status_code();
+/// # Errors
+///
+/// Returns `ApiError::Validation` when the body is not a PDF or its extraction
+/// fails the sanity check, `ApiError::Internal` without `CAL_KV`, or an error
+/// when conversion or storage fails.
+pub async fn store_pdf_override(
+    kv: Option<&KvStore>,
+    semester: i32,
+    pdf_bytes: &[u8],
+    config: &PipelineConfig,
+) -> Result<String, ApiError> {
+    if !pdf_bytes.starts_with(b"%PDF-") {
+        return Err(ApiError::Validation(
+            "request body is not a PDF document".to_string(),
+        ));
+    }
+
+    kv_store::require(kv)?;
+    let built = convert_pdf_bytes_to_csv(pdf_bytes, config)?;
+    validate_extraction(semester, &built.csv, built.warning_count, config)?;
+    kv_store::put_bytes(kv, &pdf_override_key(semester), pdf_bytes).await?;
+    put_built_csv(kv, semester, &built).await?;
+    kv_store::delete(kv, &extraction_failure_key(semester)).await?;
+    record_sync_success(kv, semester, &built.csv).await?;
+    Ok(built.csv)
+}
+
+/// # Errors
+///
+/// Returns `ApiError::Internal` without `CAL_KV`, or an error when the KV
+/// delete fails.
+pub async fn delete_pdf_override(kv: Option<&KvStore>, semester: i32) -> Result<(), ApiError> {
+    kv_store::require(kv)?;
+    kv_store::delete(kv, &pdf_override_key(semester)).await
+}
+
+async fn build_csv_for_link(
+    kv: Option<&KvStore>,
+    link: &SemesterLink,
+    config: &PipelineConfig,
+    budget: Option<ExtractionBudget>,
+) -> Result<BuiltCsv, ApiError> {
+    let pdf_bytes = if let Some(override_bytes) =
+        kv_store::get_bytes(kv, &pdf_override_key(link.semester)).await?
+    {
+        override_bytes
+    } else {
+        let pdf_bytes = fetch_pdf_bytes(&config.http_client(), &link.url).await?;
+        if let Err(error) = revisions::record_revision(kv, link, &pdf_bytes).await {
+            worker::console_error!(
+                "revision tracking failed for semester {}: {}",
+                link.semester,
+                error
+            );
+        }
+        pdf_bytes
+    };
+    match budget {
+        Some(budget) => convert_within_budget(&pdf_bytes, config, budget).await,
+        None => convert_pdf_bytes_to_csv(&pdf_bytes, config),
+    }
+}
+
+/// Notes are written alongside the cached CSV, so a semester that has not been
+/// built yet has none.
+///
+/// # Errors
+///
+/// Returns an error when the KV read fails or the stored notes are not valid
+/// JSON.
+pub async fn get_notes(kv: Option<&KvStore>, semester: i32) -> Result<Vec<String>, ApiError> {
+    Ok(kv_store::get_json::<Vec<String>>(kv, &notes_key(semester))
+        .await?
+        .unwrap_or_default())
+}
+
+/// # Errors
+///
+/// Returns an error when the request fails, and `ApiError::Upstream` for an
+/// error status or an empty body.
+pub async fn fetch_pdf_bytes(client: &impl HttpClient, pdf_url: &str) -> Result<Vec<u8>, ApiError> {
+    let response = client.get(pdf_url).await?;
+    let status = response.status;
     if status >= 400 {
         return Err(ApiError::Upstream(format!(
             "failed to fetch PDF source: status {status}"
         )));
     }
 
-    let bytes = response.bytes().await?;
+    let bytes = response.body;
     if bytes.is_empty() {
         return Err(ApiError::Upstream("fetched PDF is empty".to_string()));
     }
     Ok(bytes)
 }
 
-fn convert_pdf_bytes_to_csv(pdf_bytes: &[u8]) -> Result<String, ApiError> {
-    let options = ExtractOptions {
+fn log_extraction_warning(warning: &ExtractWarning) {
+    worker::console_log!(
+        "calendar extraction warning: code={:?}, severity={:?}, page={:?}, message={}",
+        warning.code,
+        warning.severity,
+        warning.page,
+        warning.message
+    );
+}
+
+/// Returns `options` with a cancellation token that trips once `deadline`
+/// passes. Extraction runs synchronously on the isolate, where no timer can
+/// fire, so the deadline is checked as each page starts and finishes.
+#[must_use]
+pub fn with_deadline(options: ExtractOptions, deadline: DateTime<Utc>) -> ExtractOptions {
+    let token = CancellationToken::new();
+    ExtractOptions {
+        progress: Some(SharedProgressSink::new(DeadlineSink {
+            deadline,
+            token: token.clone(),
+        })),
+        cancellation: Some(token),
+        ..options
+    }
+}
+
+struct DeadlineSink {
+    deadline: DateTime<Utc>,
+    token: CancellationToken,
+}
+
+impl DeadlineSink {
+    fn check(&self) {
+        if Utc::now() >= self.deadline {
+            self.token.cancel();
+        }
+    }
+}
+
+impl ProgressSink for DeadlineSink {
+    fn page_started(&self, _page: u32, _index: usize, _total: usize) {
+        self.check();
+    }
+
+    fn page_finished(&self, _page: u32, _index: usize, _total: usize) {
+        self.check();
+    }
+}
+
+async fn convert_within_budget(
+    pdf_bytes: &[u8],
+    config: &PipelineConfig,
+    budget: ExtractionBudget,
+) -> Result<BuiltCsv, ApiError> {
+    let ExtractionBudget {
+        deadline,
+        exceeded,
+        released,
+    } = budget;
+    // A cancelled receiver means the route already answered during the fetch.
+    if !exceeded.is_canceled() {
+        let options = with_deadline(extraction_options(config), deadline);
+        match extract_calendar_csv(pdf_bytes, &options) {
+            Err(ExtractError::Cancelled) => {
+                let _ = exceeded.send(());
+            }
+            result => return result.map_err(|error| conversion_error(&error)),
+        }
+    }
+
+    let _ = released.await;
+    // Let the runtime send the response before extraction takes the isolate.
+    Delay::from(std::time::Duration::ZERO).await;
+    convert_pdf_bytes_to_csv(pdf_bytes, config)
+}
+
+fn extraction_options(config: &PipelineConfig) -> ExtractOptions {
+    let defaults = ExtractOptions::default();
+    ExtractOptions {
         clean_calendar: true,
         no_page: true,
         no_table: true,
-        custom_col_names: Some(("date".to_string(), "event".to_string())),
+        rename_columns: BTreeMap::from([
+            ("col_1".to_string(), "date".to_string()),
+            ("col_2".to_string(), "event".to_string()),
+        ]),
+        warning_sink: Some(SharedWarningSink::new(log_extraction_warning)),
+        calendar_category_column: true,
+        max_page_text_bytes: Some(MAX_PAGE_TEXT_BYTES),
+        continue_on_page_error: true,
+        calendar_noise_phrases: [
+            defaults.calendar_noise_phrases,
+            config.calendar_noise.clone(),
+        ]
+        .concat(),
+        calendar_cut_tokens: [defaults.calendar_cut_tokens, config.calendar_noise.clone()].concat(),
         ..ExtractOptions::default()
-    };
+    }
+}
+
+fn conversion_error(error: &ExtractError) -> ApiError {
+    ApiError::Parse(format!(
+        "failed to convert PDF using chihlee-cal-to-csv: {error}"
+    ))
+}
 
-    let (csv, report) = extract_pdf_bytes_to_csv_string(pdf_bytes, &options).map_err(|error| {
-        ApiError::Parse(format!(
-            "failed to convert PDF using chihlee-cal-to-csv: {error}"
-        ))
-    })?;
+fn convert_pdf_bytes_to_csv(
+    pdf_bytes: &[u8],
+    config: &PipelineConfig,
+) -> Result<BuiltCsv, ApiError> {
+    extract_calendar_csv(pdf_bytes, &extraction_options(config))
+        .map_err(|error| conversion_error(&error))
+}
+
+fn extract_calendar_csv(
+    pdf_bytes: &[u8],
+    options: &ExtractOptions,
+) -> Result<BuiltCsv, ExtractError> {
+    let (csv, report) = extract_pdf_bytes_to_csv_string(pdf_bytes, options)?;
 
     worker::console_log!(
-        "calendar extraction completed: rows={}, tables={}",
+        "calendar extraction completed: rows={}, tables={}, warnings={}, notes={}",
         report.row_count,
-        report.table_count
+        report.table_count,
+        report.warnings.len(),
+        report.notes.len()
     );
 
-    Ok(csv)
+    Ok(BuiltCsv {
+        csv,
+        warning_count: report.warnings.len(),
+        notes: report.notes,
+    })
 }
@@ -99,3 +99,9 @@ impl From<std::num::ParseIntError> for ApiError {
         Self::BadRequest(error.to_string())
     }
 }
+
+impl From<csv::Error> for ApiError {
+    fn from(error: csv::Error) -> Self {
+        Self::Internal(error.to_string())
+    }
+}
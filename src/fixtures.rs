@@ -0,0 +1,8 @@
+pub const SOURCE_HTML: &str = include_str!("../fixtures/source.html");
+pub const CALENDAR_PDF: &[u8] = include_bytes!("../fixtures/calendar.pdf");
+
+#[must_use]
+pub fn parse_dev_fixtures(raw: &str) -> bool {
+    let lowered = raw.trim().to_ascii_lowercase();
+    lowered == "true" || lowered == "1" || lowered == "yes"
+}
@@ -0,0 +1,86 @@
+use std::path::Path;
+
+use url::Url;
+use worker::Fetch;
+
+use crate::error::ApiError;
+use crate::fixtures;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// # Errors
+    ///
+    /// Returns `ApiError::Upstream` when the body is not valid UTF-8.
+    pub fn text(self) -> Result<String, ApiError> {
+        String::from_utf8(self.body)
+            .map_err(|_| ApiError::Upstream("response body is not valid UTF-8".to_string()))
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait HttpClient {
+    async fn get(&self, url: &str) -> Result<HttpResponse, ApiError>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FetchClient;
+
+impl HttpClient for FetchClient {
+    async fn get(&self, url: &str) -> Result<HttpResponse, ApiError> {
+        let parsed = Url::parse(url)?;
+        let mut response = Fetch::Url(parsed).send().await?;
+        Ok(HttpResponse {
+            status: response.status_code(),
+            body: response.bytes().await?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixtureClient;
+
+impl HttpClient for FixtureClient {
+    async fn get(&self, url: &str) -> Result<HttpResponse, ApiError> {
+        let parsed = Url::parse(url)?;
+        let is_pdf = Path::new(parsed.path())
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"));
+        let body = if is_pdf {
+            fixtures::CALENDAR_PDF.to_vec()
+        } else {
+            fixtures::SOURCE_HTML.as_bytes().to_vec()
+        };
+        Ok(HttpResponse { status: 200, body })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RuntimeClient {
+    Fetch(FetchClient),
+    Fixtures(FixtureClient),
+}
+
+impl RuntimeClient {
+    #[must_use]
+    pub fn new(dev_fixtures: bool) -> Self {
+        if dev_fixtures {
+            Self::Fixtures(FixtureClient)
+        } else {
+            Self::Fetch(FetchClient)
+        }
+    }
+}
+
+impl HttpClient for RuntimeClient {
+    async fn get(&self, url: &str) -> Result<HttpResponse, ApiError> {
+        match self {
+            Self::Fetch(client) => client.get(url).await,
+            Self::Fixtures(client) => client.get(url).await,
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chihlee_cal_to_csv::resolve_event_dates;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use worker::KvStore;
+
+use crate::error::ApiError;
+use crate::kv_store;
+use crate::models::ICS_UIDS_KEY_PREFIX;
+use crate::revisions::content_hash;
+
+const ICS_LINE_LIMIT: usize = 75;
+
+/// Days an event may move while its text is edited and still keep its UID.
+const UID_DATE_WINDOW_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IcsUidEntry {
+    pub uid: String,
+    pub sequence: u32,
+    pub content_hash: String,
+    /// Normalized event text the UID is matched on.
+    #[serde(default)]
+    pub text: String,
+    /// Start date (`YYYY-MM-DD`) from the last render, for matching events
+    /// whose text was edited.
+    #[serde(default)]
+    pub start: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IcsEvent {
+    pub uid: String,
+    pub sequence: u32,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub summary: String,
+}
+
+/// Registry entries keyed by UID.
+pub type IcsUidRegistry = BTreeMap<String, IcsUidEntry>;
+
+#[must_use]
+pub fn ics_uids_key(semester: i32) -> String {
+    format!("{ICS_UIDS_KEY_PREFIX}{semester}")
+}
+
+/// # Errors
+///
+/// Returns an error when the KV read fails or the registry is not valid JSON.
+pub async fn load_uid_registry(
+    kv: Option<&KvStore>,
+    semester: i32,
+) -> Result<IcsUidRegistry, ApiError> {
+    let registry = kv_store::get_json::<IcsUidRegistry>(kv, &ics_uids_key(semester))
+        .await?
+        .unwrap_or_default();
+    Ok(registry)
+}
+
+/// # Errors
+///
+/// Returns an error when the registry cannot be serialized or the KV write
+/// fails.
+pub async fn save_uid_registry(
+    kv: Option<&KvStore>,
+    semester: i32,
+    registry: &IcsUidRegistry,
+) -> Result<(), ApiError> {
+    kv_store::put_json(kv, &ics_uids_key(semester), registry).await
+}
+
+/// # Errors
+///
+/// Returns `ApiError::Internal` when the CSV has no `date` or `event` column,
+/// or an error when it does not parse.
+pub fn parse_calendar_rows(csv: &str) -> Result<Vec<(String, String)>, ApiError> {
+    let mut reader = csv::ReaderBuilder::new().from_reader(csv.as_bytes());
+    let headers = reader.headers()?.clone();
+    let (Some(date_index), Some(event_index)) = (
+        headers.iter().position(|header| header == "date"),
+        headers.iter().position(|header| header == "event"),
+    ) else {
+        return Err(ApiError::Internal(
+            "calendar csv is missing date or event columns".to_string(),
+        ));
+    };
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        if let (Some(date), Some(event)) = (record.get(date_index), record.get(event_index)) {
+            rows.push((date.to_string(), event.to_string()));
+        }
+    }
+    Ok(rows)
+}
+
+#[must_use]
+pub fn parse_event_dates(date: &str, semester: i32) -> Option<(NaiveDate, NaiveDate)> {
+    let (start, end) = resolve_event_dates(date, semester)?;
+    Some((start, end.unwrap_or(start)))
+}
+
+/// Gives each row the UID of the registry entry with the same normalized
+/// text, nearest in date when the text repeats. A row whose text matches no
+/// entry takes the nearest entry within `UID_DATE_WINDOW_DAYS` of its start,
+/// as an edited event, and otherwise gets a new UID. `SEQUENCE` is bumped
+/// when an event's date or text changes, and entries not matched by any row
+/// are dropped. Returns the events and whether the registry changed.
+pub fn assign_uids(
+    registry: &mut IcsUidRegistry,
+    semester: i32,
+    rows: &[(String, String)],
+) -> (Vec<IcsEvent>, bool) {
+    let rows = rows
+        .iter()
+        .filter_map(|(date, summary)| {
+            let (start, end) = parse_event_dates(date, semester)?;
+            Some((start, end, summary, normalize_event_text(summary)))
+        })
+        .collect::<Vec<_>>();
+    let mut unmatched = registry.keys().cloned().collect::<BTreeSet<_>>();
+    let mut uids = vec![None; rows.len()];
+
+    for (uid, (start, _, _, text)) in uids.iter_mut().zip(&rows) {
+        *uid = take_nearest_entry(registry, &mut unmatched, *start, None, |entry| {
+            entry.text == *text
+        });
+    }
+    for (uid, (start, ..)) in uids.iter_mut().zip(&rows) {
+        if uid.is_none() {
+            *uid = take_nearest_entry(
+                registry,
+                &mut unmatched,
+                *start,
+                Some(UID_DATE_WINDOW_DAYS),
+                |_| true,
+            );
+        }
+    }
+
+    let mut changed = !unmatched.is_empty();
+    for uid in &unmatched {
+        registry.remove(uid);
+    }
+
+    let mut events = Vec::with_capacity(rows.len());
+    for (uid, (start, end, summary, text)) in uids.into_iter().zip(rows) {
+        let hash = content_hash(format!("{start}~{end}\n{summary}").as_bytes());
+        let uid = uid.unwrap_or_else(|| {
+            changed = true;
+            let uid = new_uid(registry, semester, &text, start);
+            registry.insert(
+                uid.clone(),
+                IcsUidEntry {
+                    uid: uid.clone(),
+                    sequence: 0,
+                    content_hash: hash.clone(),
+                    text: text.clone(),
+                    start: start.to_string(),
+                },
+            );
+            uid
+        });
+        let Some(entry) = registry.get_mut(&uid) else {
+            continue;
+        };
+        if entry.content_hash != hash {
+            entry.sequence += 1;
+            entry.content_hash.clone_from(&hash);
+            changed = true;
+        }
+        entry.text = text;
+        entry.start = start.to_string();
+
+        events.push(IcsEvent {
+            uid,
+            sequence: entry.sequence,
+            start,
+            end,
+            summary: summary.clone(),
+        });
+    }
+
+    (events, changed)
+}
+
+/// Event text with whitespace removed and letters lowercased, so reflowed
+/// or recased text keeps its UID.
+fn normalize_event_text(summary: &str) -> String {
+    summary
+        .chars()
+        .filter(|ch| !ch.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Removes and returns the unmatched entry accepted by `accepts` whose start
+/// is nearest to `start`, no further than `window_days` when given.
+fn take_nearest_entry(
+    registry: &IcsUidRegistry,
+    unmatched: &mut BTreeSet<String>,
+    start: NaiveDate,
+    window_days: Option<i64>,
+    accepts: impl Fn(&IcsUidEntry) -> bool,
+) -> Option<String> {
+    let uid = unmatched
+        .iter()
+        .filter_map(|uid| {
+            let entry = registry.get(uid).filter(|entry| accepts(entry))?;
+            let entry_start = NaiveDate::parse_from_str(&entry.start, "%Y-%m-%d").ok()?;
+            let distance = (entry_start - start).num_days().abs();
+            window_days
+                .is_none_or(|window| distance <= window)
+                .then_some((distance, uid))
+        })
+        .min()
+        .map(|(_, uid)| uid.clone())?;
+    unmatched.remove(&uid);
+    Some(uid)
+}
+
+fn new_uid(registry: &IcsUidRegistry, semester: i32, text: &str, start: NaiveDate) -> String {
+    let mut attempt = 0_u32;
+    loop {
+        let seed = format!("{text}#{start}#{attempt}");
+        let uid = format!(
+            "{semester}-{}@chihlee-cal-worker",
+            &content_hash(seed.as_bytes())[..16]
+        );
+        if !registry.contains_key(&uid) {
+            return uid;
+        }
+        attempt += 1;
+    }
+}
+
+#[must_use]
+pub fn render_ics(semester: i32, events: &[IcsEvent], generated_at: DateTime<Utc>) -> String {
+    let stamp = generated_at.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//chihlee-cal-worker//Academic Calendar//ZH-TW".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:致理科技大學 {semester} 學年度行事曆"),
+    ];
+
+    for event in events {
+        let end_exclusive = event.end + Duration::days(1);
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", event.uid),
+            format!("SEQUENCE:{}", event.sequence),
+            format!("DTSTAMP:{stamp}"),
+            format!("DTSTART;VALUE=DATE:{}", event.start.format("%Y%m%d")),
+            format!("DTEND;VALUE=DATE:{}", end_exclusive.format("%Y%m%d")),
+            format!("SUMMARY:{}", escape_text(&event.summary)),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold_line(line))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut width = 0;
+    for ch in line.chars() {
+        if width + ch.len_utf8() > ICS_LINE_LIMIT {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(ch);
+        width += ch.len_utf8();
+    }
+    folded
+}
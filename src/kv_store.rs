@@ -0,0 +1,103 @@
+//! Every helper takes `CAL_KV` as an `Option`: without the binding, reads
+//! find nothing and writes are skipped, so the calendar routes and the cron
+//! keep working from the Cache API alone. Callers whose whole purpose is to
+//! persist something go through `require` instead.
+
+use serde::{Serialize, de::DeserializeOwned};
+use worker::KvStore;
+
+use crate::error::ApiError;
+use crate::models::KV_BINDING;
+
+/// # Errors
+///
+/// Returns `ApiError::Internal` when the `CAL_KV` binding is not configured.
+pub fn require(kv: Option<&KvStore>) -> Result<&KvStore, ApiError> {
+    kv.ok_or_else(|| ApiError::Internal(format!("{KV_BINDING} binding is not configured")))
+}
+
+/// # Errors
+///
+/// Returns an error when the KV read fails or the value is not valid JSON.
+pub async fn get_json<T>(kv: Option<&KvStore>, key: &str) -> Result<Option<T>, ApiError>
+where
+    T: DeserializeOwned,
+{
+    let Some(kv) = kv else {
+        return Ok(None);
+    };
+    let value = kv.get(key).json::<T>().await?;
+    Ok(value)
+}
+
+/// # Errors
+///
+/// Returns an error when the value cannot be serialized or the KV write fails.
+pub async fn put_json<T>(kv: Option<&KvStore>, key: &str, value: &T) -> Result<(), ApiError>
+where
+    T: Serialize,
+{
+    let Some(kv) = kv else {
+        return Ok(());
+    };
+    let body = serde_json::to_string(value)?;
+    kv.put(key, body)?.execute().await?;
+    Ok(())
+}
+
+/// # Errors
+///
+/// Returns an error when the KV read fails.
+pub async fn get_bytes(kv: Option<&KvStore>, key: &str) -> Result<Option<Vec<u8>>, ApiError> {
+    let Some(kv) = kv else {
+        return Ok(None);
+    };
+    let value = kv.get(key).bytes().await?;
+    Ok(value)
+}
+
+/// # Errors
+///
+/// Returns an error when the KV write fails.
+pub async fn put_bytes(kv: Option<&KvStore>, key: &str, bytes: &[u8]) -> Result<(), ApiError> {
+    let Some(kv) = kv else {
+        return Ok(());
+    };
+    kv.put_bytes(key, bytes)?.execute().await?;
+    Ok(())
+}
+
+/// # Errors
+///
+/// Returns an error when the KV delete fails.
+pub async fn delete(kv: Option<&KvStore>, key: &str) -> Result<(), ApiError> {
+    let Some(kv) = kv else {
+        return Ok(());
+    };
+    kv.delete(key).await?;
+    Ok(())
+}
+
+/// # Errors
+///
+/// Returns an error when a KV list page cannot be read.
+pub async fn list_keys(kv: Option<&KvStore>, prefix: &str) -> Result<Vec<String>, ApiError> {
+    let Some(kv) = kv else {
+        return Ok(Vec::new());
+    };
+    let mut names = Vec::new();
+    let mut cursor = None;
+    loop {
+        let mut request = kv.list().prefix(prefix.to_string());
+        if let Some(cursor) = cursor.take() {
+            request = request.cursor(cursor);
+        }
+
+        let page = request.execute().await?;
+        names.extend(page.keys.into_iter().map(|key| key.name));
+        if page.list_complete || page.cursor.is_none() {
+            return Ok(names);
+        }
+        cursor = page.cursor;
+    }
+}
@@ -1,9 +1,18 @@
 pub mod cache;
+pub mod compare;
+pub mod corrections;
 pub mod csv_pipeline;
 pub mod error;
+pub mod fixtures;
+pub mod http_client;
+pub mod ics;
+pub mod kv_store;
 pub mod models;
+pub mod revisions;
 pub mod routes;
 pub mod source_scraper;
+pub mod static_assets;
+pub mod variants;
 
 use worker::{Context, Env, Request, Response, Result, ScheduleContext, ScheduledEvent, event};
 
@@ -19,7 +28,20 @@ async fn scheduled(_event: ScheduledEvent, env: Env, _ctx: ScheduleContext) {
         .map(|value| value.to_string())
         .unwrap_or_else(|_| models::DEFAULT_SOURCE_URL.to_string());
 
-    if let Err(error) = csv_pipeline::sync_all_semesters(&source_url).await {
+    let config = csv_pipeline::PipelineConfig::from_env(&env);
+
+    let result = match env.kv(models::KV_BINDING) {
+        Ok(kv) => csv_pipeline::sync_next_semester(&source_url, &kv, &config).await,
+        Err(error) => {
+            worker::console_warn!(
+                "{} is not bound ({error}); refreshing every semester",
+                models::KV_BINDING
+            );
+            csv_pipeline::sync_all_semesters(&source_url, &config).await
+        }
+    };
+
+    if let Err(error) = result {
         worker::console_error!("scheduled csv sync failed: {error}");
     }
 }
@@ -3,12 +3,36 @@ use serde::{Deserialize, Serialize};
 pub const DEFAULT_SOURCE_URL: &str = "https://www.chihlee.edu.tw/p/404-1000-62149.php";
 pub const LINKS_CACHE_KEY: &str = "cal:links:v1";
 pub const LINKS_CACHE_TTL_SECONDS: u32 = 6 * 60 * 60;
+pub const UPSTREAM_STATUS_CACHE_KEY: &str = "cal:upstream-status:v1";
+pub const UPSTREAM_STATUS_CACHE_TTL_SECONDS: u32 = 5 * 60;
+pub const KV_BINDING: &str = "CAL_KV";
+pub const SYNC_CURSOR_KEY: &str = "cal:sync:cursor:v1";
+pub const REVISIONS_KEY_PREFIX: &str = "cal:revisions:v1:";
+pub const PDF_OVERRIDE_KEY_PREFIX: &str = "cal:pdf-override:v1:";
+pub const CORRECTIONS_KEY_PREFIX: &str = "cal:corrections:v1:";
+pub const EXTRACTION_FAILURE_KEY_PREFIX: &str = "cal:extraction-failure:v1:";
+pub const CANARY_KEY_PREFIX: &str = "cal:canary:v1:";
+pub const SYNC_SUCCESS_KEY_PREFIX: &str = "cal:sync-success:v1:";
+pub const NOTES_KEY_PREFIX: &str = "cal:notes:v1:";
+pub const ICS_UIDS_KEY_PREFIX: &str = "cal:ics-uids:v1:";
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SemesterLink {
     pub semester: i32,
     pub url: String,
     pub title: String,
+    #[serde(default)]
+    pub revision: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PdfRevision {
+    pub revision: u32,
+    pub content_hash: String,
+    pub url: String,
+    pub observed_at: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -42,6 +66,158 @@ pub struct CalLinkAllResponse {
     pub cached: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RevisionsResponse {
+    pub semester: i32,
+    pub items: Vec<PdfRevision>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PdfOverrideResponse {
+    pub semester: i32,
+    pub active: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CorrectionAction {
+    Replace,
+    Suppress,
+    Clear,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EventCorrection {
+    pub date: String,
+    pub event: String,
+    pub action: CorrectionAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_event: Option<String>,
+}
+
+impl EventCorrection {
+    #[must_use]
+    pub fn matches(&self, date: &str, event: &str) -> bool {
+        self.date == date && self.event == event
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CorrectionsPatchRequest {
+    pub corrections: Vec<EventCorrection>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CorrectionsResponse {
+    pub semester: i32,
+    pub items: Vec<EventCorrection>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExtractionFailure {
+    pub semester: i32,
+    pub reason: String,
+    pub observed_at: String,
+    #[serde(default)]
+    pub degraded_in_cache: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CanaryRecord {
+    pub semester: i32,
+    pub cached_rows: usize,
+    pub canary_rows: usize,
+    pub observed_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NotesResponse {
+    pub semester: i32,
+    pub items: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CompareEvent {
+    pub date: String,
+    pub event: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ShiftedEvent {
+    pub from_date: String,
+    pub to_date: String,
+    pub from_event: String,
+    pub to_event: String,
+    pub shift_days: Option<i64>,
+    pub similarity: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompareResponse {
+    pub from: i32,
+    pub to: i32,
+    pub shifted: Vec<ShiftedEvent>,
+    pub added: Vec<CompareEvent>,
+    pub removed: Vec<CompareEvent>,
+    pub unchanged_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BuildingResponse {
+    pub semester: i32,
+    pub status: String,
+    pub retry_after_seconds: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StatusResponse {
+    pub status: ServiceHealth,
+    pub version: String,
+    pub checked_at: String,
+    pub upstream: UpstreamStatus,
+    pub upstream_cache_age_seconds: Option<i64>,
+    pub links_cache_age_seconds: Option<i64>,
+    pub semesters: Vec<SemesterStatus>,
+    pub canaries: Vec<CanaryRecord>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceHealth {
+    Ok,
+    Degraded,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UpstreamStatus {
+    pub reachable: bool,
+    pub status: Option<u16>,
+    pub latency_ms: Option<i64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SyncSuccess {
+    pub semester: i32,
+    pub rows: usize,
+    pub synced_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SemesterStatus {
+    pub semester: i32,
+    pub last_success: Option<SyncSuccess>,
+    pub last_failure: Option<ExtractionFailure>,
+    pub csv_cache_age_seconds: Option<i64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ErrorResponse {
     pub code: String,
@@ -0,0 +1,99 @@
+use std::fmt::Write;
+
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use worker::KvStore;
+
+use crate::error::ApiError;
+use crate::kv_store;
+use crate::models::{PdfRevision, REVISIONS_KEY_PREFIX, SemesterLink};
+
+#[must_use]
+pub fn revisions_key(semester: i32) -> String {
+    format!("{REVISIONS_KEY_PREFIX}{semester}")
+}
+
+#[must_use]
+pub fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+#[must_use]
+pub fn next_revision(
+    history: &[PdfRevision],
+    content_hash: &str,
+    url: &str,
+    observed_at: &str,
+) -> Option<PdfRevision> {
+    if history
+        .last()
+        .is_some_and(|latest| latest.content_hash == content_hash)
+    {
+        return None;
+    }
+
+    let revision = history.last().map_or(1, |latest| latest.revision + 1);
+    Some(PdfRevision {
+        revision,
+        content_hash: content_hash.to_string(),
+        url: url.to_string(),
+        observed_at: observed_at.to_string(),
+    })
+}
+
+/// # Errors
+///
+/// Returns an error when the KV read fails or the history is not valid JSON.
+pub async fn load_revisions(
+    kv: Option<&KvStore>,
+    semester: i32,
+) -> Result<Vec<PdfRevision>, ApiError> {
+    let history = kv_store::get_json::<Vec<PdfRevision>>(kv, &revisions_key(semester))
+        .await?
+        .unwrap_or_default();
+    Ok(history)
+}
+
+/// # Errors
+///
+/// Returns an error when the KV read or write fails.
+pub async fn record_revision(
+    kv: Option<&KvStore>,
+    link: &SemesterLink,
+    pdf_bytes: &[u8],
+) -> Result<PdfRevision, ApiError> {
+    let mut history = load_revisions(kv, link.semester).await?;
+    let hash = content_hash(pdf_bytes);
+    let observed_at = Utc::now().to_rfc3339();
+
+    let Some(revision) = next_revision(&history, &hash, &link.url, &observed_at) else {
+        return history
+            .pop()
+            .ok_or_else(|| ApiError::Internal("revision history is empty".to_string()));
+    };
+
+    history.push(revision.clone());
+    kv_store::put_json(kv, &revisions_key(link.semester), &history).await?;
+    Ok(revision)
+}
+
+/// # Errors
+///
+/// Returns an error when a KV read fails.
+pub async fn annotate_links(
+    kv: Option<&KvStore>,
+    links: &mut [SemesterLink],
+) -> Result<(), ApiError> {
+    for link in links {
+        if let Some(latest) = load_revisions(kv, link.semester).await?.pop() {
+            link.revision = latest.revision;
+            link.content_hash = Some(latest.content_hash);
+        }
+    }
+    Ok(())
+}
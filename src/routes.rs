@@ -1,139 +1,604 @@
 use std::collections::HashMap;
+use std::rc::Rc;
 
 use chrono::{DateTime, Datelike, Duration, Utc};
 use serde::Serialize;
-use worker::{Context, Env, Request, Response, Result, RouteContext, Router};
+use worker::{Context, EncodeBody, Env, KvStore, Request, Response, Result, RouteContext, Router};
 
 use crate::cache;
-use crate::csv_pipeline;
+use crate::compare;
+use crate::corrections;
+use crate::csv_pipeline::{self, BudgetedCsv, CsvCacheStatus};
 use crate::error::ApiError;
+use crate::ics;
 use crate::models::{
-    CalLinkAllResponse, CalLinkSingleResponse, CurrentSemesterResponse, LINKS_CACHE_KEY,
-    LINKS_CACHE_TTL_SECONDS, ResolvedBy, SemesterLink,
+    BuildingResponse, CalLinkAllResponse, CalLinkSingleResponse, CanaryRecord,
+    CorrectionsPatchRequest, CorrectionsResponse, CurrentSemesterResponse, KV_BINDING,
+    LINKS_CACHE_KEY, LINKS_CACHE_TTL_SECONDS, NotesResponse, PdfOverrideResponse, ResolvedBy,
+    RevisionsResponse, SemesterLink, SemesterStatus, ServiceHealth, StatusResponse,
+    UPSTREAM_STATUS_CACHE_KEY, UPSTREAM_STATUS_CACHE_TTL_SECONDS, UpstreamStatus,
 };
+use crate::revisions;
 use crate::source_scraper;
+use crate::static_assets;
+use crate::variants::{self, ContentEncoding, OutputFormat, Variant};
 
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub source_url: String,
+    pub admin_token: Option<String>,
+    pub pipeline: csv_pipeline::PipelineConfig,
+    pub context: Rc<Context>,
 }
 
-pub async fn handle(req: Request, env: Env, _ctx: Context) -> Result<Response> {
+const BUILD_RETRY_AFTER_SECONDS: u32 = 30;
+
+/// # Errors
+///
+/// Returns an error only when a response cannot be built; route failures are
+/// answered with JSON error bodies.
+pub async fn handle(req: Request, env: Env, ctx: Context) -> Result<Response> {
     let source_url = env
         .var("SOURCE_URL")
         .map(|value| value.to_string())
         .unwrap_or_else(|_| crate::models::DEFAULT_SOURCE_URL.to_string());
 
-    let state = AppState { source_url };
+    let admin_token = env
+        .secret("ADMIN_TOKEN")
+        .map(|value| value.to_string())
+        .ok()
+        .filter(|value| !value.is_empty());
+
+    let pipeline = csv_pipeline::PipelineConfig::from_env(&env);
+
+    let state = AppState {
+        source_url,
+        admin_token,
+        pipeline,
+        context: Rc::new(ctx),
+    };
 
     Router::with_data(state)
         .get_async("/api/v1/current_semester", current_semester_route)
         .get_async("/api/v1/cal_link", cal_link_route)
         .get_async("/api/v1/csv", csv_route)
+        .get_async("/api/v1/ics", ics_route)
+        .get_async("/api/v1/revisions", revisions_route)
+        .get_async("/api/v1/notes", notes_route)
+        .get_async("/api/v1/compare", compare_route)
+        .get_async("/api/v1/status", status_route)
+        .put_async("/api/v1/admin/pdf", admin_pdf_put_route)
+        .delete_async("/api/v1/admin/pdf", admin_pdf_delete_route)
+        .patch_async("/api/v1/admin/events", admin_events_patch_route)
+        .post_async("/api/v1/admin/canary/promote", admin_canary_promote_route)
+        .get_async("/robots.txt", robots_route)
+        .get_async("/sitemap.xml", sitemap_route)
+        .get_async("/favicon.ico", favicon_route)
         .run(req, env)
         .await
 }
 
-async fn current_semester_route(_req: Request, ctx: RouteContext<AppState>) -> Result<Response> {
-    match current_semester_response(&ctx.data.source_url).await {
-        Ok(response) => json_response(&response),
+async fn current_semester_route(req: Request, ctx: RouteContext<AppState>) -> Result<Response> {
+    let kv = ctx.kv(KV_BINDING).ok();
+    match current_semester_response(&ctx.data, kv.as_ref()).await {
+        Ok((response, etag)) => conditional_json_response(&req, &response, &etag),
         Err(error) => error.into_response(),
     }
 }
 
 async fn cal_link_route(req: Request, ctx: RouteContext<AppState>) -> Result<Response> {
-    match cal_link_response(&req, &ctx.data.source_url).await {
-        Ok(response) => json_response(&response),
+    let kv = ctx.kv(KV_BINDING).ok();
+    match cal_link_response(&req, &ctx.data, kv.as_ref()).await {
+        Ok((response, etag)) => conditional_json_response(&req, &response, &etag),
         Err(error) => error.into_response(),
     }
 }
 
 async fn csv_route(req: Request, ctx: RouteContext<AppState>) -> Result<Response> {
-    match csv_response(&req, &ctx.data.source_url).await {
+    let kv = ctx.kv(KV_BINDING).ok();
+    match calendar_response(&req, &ctx.data, kv.as_ref(), OutputFormat::Csv).await {
+        Ok(response) => Ok(response),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn ics_route(req: Request, ctx: RouteContext<AppState>) -> Result<Response> {
+    let kv = ctx.kv(KV_BINDING).ok();
+    match calendar_response(&req, &ctx.data, kv.as_ref(), OutputFormat::Ics).await {
         Ok(response) => Ok(response),
         Err(error) => error.into_response(),
     }
 }
 
-async fn current_semester_response(source_url: &str) -> Result<CurrentSemesterResponse, ApiError> {
-    let (links, cached) = load_links(source_url).await?;
+async fn revisions_route(req: Request, ctx: RouteContext<AppState>) -> Result<Response> {
+    let kv = ctx.kv(KV_BINDING).ok();
+    match revisions_response(&req, &ctx.data, kv.as_ref()).await {
+        Ok(response) => json_response(&response),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn notes_route(req: Request, ctx: RouteContext<AppState>) -> Result<Response> {
+    let kv = ctx.kv(KV_BINDING).ok();
+    match notes_response(&req, &ctx.data, kv.as_ref()).await {
+        Ok(response) => json_response(&response),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn compare_route(req: Request, ctx: RouteContext<AppState>) -> Result<Response> {
+    let kv = ctx.kv(KV_BINDING).ok();
+    match compare_response(&req, &ctx.data, kv.as_ref()).await {
+        Ok(response) => Ok(response),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn admin_pdf_put_route(mut req: Request, ctx: RouteContext<AppState>) -> Result<Response> {
+    let kv = ctx.kv(KV_BINDING).ok();
+    match admin_pdf_put_response(&mut req, &ctx.data, kv.as_ref()).await {
+        Ok(response) => json_response(&response),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn admin_pdf_delete_route(req: Request, ctx: RouteContext<AppState>) -> Result<Response> {
+    let kv = ctx.kv(KV_BINDING).ok();
+    match admin_pdf_delete_response(&req, &ctx.data, kv.as_ref()).await {
+        Ok(response) => json_response(&response),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn admin_events_patch_route(
+    mut req: Request,
+    ctx: RouteContext<AppState>,
+) -> Result<Response> {
+    let kv = ctx.kv(KV_BINDING).ok();
+    match admin_events_patch_response(&mut req, &ctx.data, kv.as_ref()).await {
+        Ok(response) => json_response(&response),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn status_route(_req: Request, ctx: RouteContext<AppState>) -> Result<Response> {
+    let kv = ctx.kv(KV_BINDING).ok();
+    match status_response(&ctx.data, kv.as_ref()).await {
+        Ok(response) => json_response(&response),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn admin_canary_promote_route(req: Request, ctx: RouteContext<AppState>) -> Result<Response> {
+    let kv = ctx.kv(KV_BINDING).ok();
+    match admin_canary_promote_response(&req, &ctx.data, kv.as_ref()).await {
+        Ok(response) => json_response(&response),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn robots_route(req: Request, _ctx: RouteContext<AppState>) -> Result<Response> {
+    let origin = req.url()?.origin().ascii_serialization();
+    static_response(
+        static_assets::robots_txt(&origin).into_bytes(),
+        "text/plain; charset=utf-8",
+    )
+}
+
+async fn sitemap_route(req: Request, _ctx: RouteContext<AppState>) -> Result<Response> {
+    let origin = req.url()?.origin().ascii_serialization();
+    static_response(
+        static_assets::sitemap_xml(&origin).into_bytes(),
+        "application/xml; charset=utf-8",
+    )
+}
+
+async fn favicon_route(_req: Request, _ctx: RouteContext<AppState>) -> Result<Response> {
+    static_response(static_assets::FAVICON_ICO.to_vec(), "image/x-icon")
+}
+
+fn static_response(body: Vec<u8>, content_type: &str) -> Result<Response> {
+    let mut response = Response::from_bytes(body)?;
+    response.headers_mut().set("Content-Type", content_type)?;
+    response.headers_mut().set(
+        "Cache-Control",
+        &format!("public, max-age={}", static_assets::STATIC_CACHE_SECONDS),
+    )?;
+    Ok(response)
+}
+
+async fn current_semester_response(
+    state: &AppState,
+    kv: Option<&KvStore>,
+) -> Result<(CurrentSemesterResponse, String), ApiError> {
+    let (links, cached) = load_links(state, kv).await?;
     let latest_available = latest_semester(&links)?;
     let (roc_year, target) = current_roc_year_and_target_now();
     let semester = resolve_current_semester(target, &links);
+    let etag = links_etag(&links, target)?;
 
-    Ok(CurrentSemesterResponse {
-        semester,
-        roc_year,
-        latest_available,
-        source_url: source_url.to_string(),
-        cached,
-    })
+    Ok((
+        CurrentSemesterResponse {
+            semester,
+            roc_year,
+            latest_available,
+            source_url: state.source_url.clone(),
+            cached,
+        },
+        etag,
+    ))
 }
 
 async fn cal_link_response(
     req: &Request,
-    source_url: &str,
-) -> Result<CalLinkResponseEnvelope, ApiError> {
+    state: &AppState,
+    kv: Option<&KvStore>,
+) -> Result<(CalLinkResponseEnvelope, String), ApiError> {
     let query = parse_query(req)?;
     let semester_param = parse_semester_query(&query)?;
     let all = parse_all_query(&query);
 
-    let (links, cached) = load_links(source_url).await?;
+    let (links, cached) = load_links(state, kv).await?;
+    let target = current_target_semester_now();
+    let etag = links_etag(&links, target)?;
 
     if all {
-        return Ok(CalLinkResponseEnvelope::All(CalLinkAllResponse {
-            items: links,
-            cached,
-        }));
+        return Ok((
+            CalLinkResponseEnvelope::All(CalLinkAllResponse {
+                items: links,
+                cached,
+            }),
+            etag,
+        ));
     }
 
-    let target = current_target_semester_now();
     let selected = resolve_selected_semester(semester_param, &links, target)?;
     let link = find_link(&links, selected.semester)
         .ok_or_else(|| ApiError::NotFound("requested semester link not found".to_string()))?;
 
-    Ok(CalLinkResponseEnvelope::Single(CalLinkSingleResponse {
-        semester: link.semester,
-        url: link.url.clone(),
-        resolved_by: selected.resolved_by,
-        cached,
-    }))
+    Ok((
+        CalLinkResponseEnvelope::Single(CalLinkSingleResponse {
+            semester: link.semester,
+            url: link.url.clone(),
+            resolved_by: selected.resolved_by,
+            cached,
+        }),
+        etag,
+    ))
 }
 
-async fn csv_response(req: &Request, source_url: &str) -> Result<Response, ApiError> {
+async fn calendar_response(
+    req: &Request,
+    state: &AppState,
+    kv: Option<&KvStore>,
+    default_format: OutputFormat,
+) -> Result<Response, ApiError> {
     let query = parse_query(req)?;
     let semester_param = parse_semester_query(&query)?;
     let force = parse_force_query(&query);
-    let (links, _) = load_links(source_url).await?;
+    let variant = Variant::negotiate(
+        req.headers().get("Accept")?.as_deref(),
+        req.headers().get("Accept-Encoding")?.as_deref(),
+        default_format,
+    );
+    let (links, _) = load_links(state, kv).await?;
     let target = current_target_semester_now();
     let selected = resolve_selected_semester(semester_param, &links, target)?;
     let link = find_link(&links, selected.semester)
         .ok_or_else(|| ApiError::NotFound("requested semester link not found".to_string()))?;
 
-    let (csv, cache_status) = if force {
-        csv_pipeline::rebuild_csv_for_link_with_status(link).await?
-    } else {
-        csv_pipeline::get_or_build_csv_for_link_with_status(link).await?
+    let built =
+        csv_pipeline::build_csv_within_budget(&state.context, kv, link, &state.pipeline, force)
+            .await?;
+    // The handoff lives until this response is built; see `BuildHandoff`.
+    let (csv, cache_status, _handoff) = match built {
+        BudgetedCsv::Ready(csv, cache_status) => (csv, cache_status, None),
+        BudgetedCsv::Stale(csv, handoff) => (csv, CsvCacheStatus::Stale, Some(handoff)),
+        BudgetedCsv::Building(_handoff) => return building_response(link.semester),
+    };
+    let body = match variant.format {
+        OutputFormat::Csv => csv,
+        OutputFormat::Ics => render_ics_for_link(kv, link, &csv).await?,
     };
-    let mut response = Response::ok(csv)?;
+    let payload = variants::encoded_body(
+        &csv_pipeline::csv_cache_key(link.semester),
+        variant,
+        body.into_bytes(),
+    )
+    .await?;
+
+    let mut response = Response::from_bytes(payload)?;
     response
         .headers_mut()
-        .set("Content-Type", "text/csv; charset=utf-8")?;
+        .set("Content-Type", variant.format.content_type())?;
     response.headers_mut().set(
         "Content-Disposition",
         &format!(
-            "inline; filename=\"chihlee-calendar-{}.csv\"",
-            link.semester
+            "inline; filename=\"chihlee-calendar-{}.{}\"",
+            link.semester,
+            variant.format.extension()
         ),
     )?;
     response
         .headers_mut()
         .set("X-Cache-Status", cache_status.as_header_value())?;
     response.headers_mut().set("Cache-Control", "no-store")?;
+    response
+        .headers_mut()
+        .set("Vary", variants::VARY_HEADER_VALUE)?;
+    if variant.encoding == ContentEncoding::Gzip {
+        response
+            .headers_mut()
+            .set("Content-Encoding", variant.encoding.as_header_value())?;
+        return Ok(response.with_encode_body(EncodeBody::Manual));
+    }
+    Ok(response)
+}
+
+fn building_response(semester: i32) -> Result<Response, ApiError> {
+    let mut response = Response::from_json(&BuildingResponse {
+        semester,
+        status: "building".to_string(),
+        retry_after_seconds: BUILD_RETRY_AFTER_SECONDS,
+    })?
+    .with_status(202);
+    response
+        .headers_mut()
+        .set("Retry-After", &BUILD_RETRY_AFTER_SECONDS.to_string())?;
+    response.headers_mut().set("Cache-Control", "no-store")?;
     Ok(response)
 }
 
-async fn load_links(source_url: &str) -> Result<(Vec<SemesterLink>, bool), ApiError> {
+async fn render_ics_for_link(
+    kv: Option<&KvStore>,
+    link: &SemesterLink,
+    csv: &str,
+) -> Result<String, ApiError> {
+    let rows = ics::parse_calendar_rows(csv)?;
+    let mut registry = ics::load_uid_registry(kv, link.semester).await?;
+    let (events, changed) = ics::assign_uids(&mut registry, link.semester, &rows);
+    if changed {
+        ics::save_uid_registry(kv, link.semester, &registry).await?;
+    }
+    // Stamping events with the CSV's build time rather than the request time
+    // keeps the body, and so its gzip variant's cache key, stable.
+    let generated_at = cache::cached_at(&csv_pipeline::csv_cache_key(link.semester))
+        .await?
+        .unwrap_or_else(Utc::now);
+    Ok(ics::render_ics(link.semester, &events, generated_at))
+}
+
+async fn revisions_response(
+    req: &Request,
+    state: &AppState,
+    kv: Option<&KvStore>,
+) -> Result<RevisionsResponse, ApiError> {
+    let query = parse_query(req)?;
+    let semester_param = parse_semester_query(&query)?;
+    let (links, _) = load_links(state, kv).await?;
+    let target = current_target_semester_now();
+    let selected = resolve_selected_semester(semester_param, &links, target)?;
+    let items = revisions::load_revisions(kv, selected.semester).await?;
+
+    Ok(RevisionsResponse {
+        semester: selected.semester,
+        items,
+    })
+}
+
+async fn notes_response(
+    req: &Request,
+    state: &AppState,
+    kv: Option<&KvStore>,
+) -> Result<NotesResponse, ApiError> {
+    let query = parse_query(req)?;
+    let semester_param = parse_semester_query(&query)?;
+    let (links, _) = load_links(state, kv).await?;
+    let target = current_target_semester_now();
+    let selected = resolve_selected_semester(semester_param, &links, target)?;
+    let link = find_link(&links, selected.semester)
+        .ok_or_else(|| ApiError::NotFound("requested semester link not found".to_string()))?;
+    let items = csv_pipeline::get_notes(kv, link.semester).await?;
+
+    Ok(NotesResponse {
+        semester: link.semester,
+        items,
+    })
+}
+
+async fn compare_response(
+    req: &Request,
+    state: &AppState,
+    kv: Option<&KvStore>,
+) -> Result<Response, ApiError> {
+    let query = parse_query(req)?;
+    let from = parse_semester_param(&query, "from")?
+        .ok_or_else(|| ApiError::BadRequest("from query parameter is required".to_string()))?;
+    let to = parse_semester_param(&query, "to")?
+        .ok_or_else(|| ApiError::BadRequest("to query parameter is required".to_string()))?;
+
+    let (links, _) = load_links(state, kv).await?;
+    let mut rows = Vec::with_capacity(2);
+    // Handoffs live until this response is built; see `BuildHandoff`.
+    let mut handoffs = Vec::new();
+    for semester in [from, to] {
+        let link = find_link(&links, semester)
+            .ok_or_else(|| ApiError::NotFound(format!("semester {semester} link not found")))?;
+        let built =
+            csv_pipeline::build_csv_within_budget(&state.context, kv, link, &state.pipeline, false)
+                .await?;
+        let csv = match built {
+            BudgetedCsv::Ready(csv, _) => csv,
+            BudgetedCsv::Stale(csv, handoff) => {
+                handoffs.push(handoff);
+                csv
+            }
+            BudgetedCsv::Building(_handoff) => return building_response(semester),
+        };
+        rows.push(ics::parse_calendar_rows(&csv)?);
+    }
+
+    let response = compare::compare_calendars(from, &rows[0], to, &rows[1]);
+    Ok(json_response(&response)?)
+}
+
+async fn admin_pdf_put_response(
+    req: &mut Request,
+    state: &AppState,
+    kv: Option<&KvStore>,
+) -> Result<PdfOverrideResponse, ApiError> {
+    authorize_admin(req, state)?;
+    let semester = require_semester_query(&parse_query(req)?)?;
+    let pdf_bytes = req.bytes().await?;
+    if pdf_bytes.is_empty() {
+        return Err(ApiError::BadRequest("request body is empty".to_string()));
+    }
+
+    csv_pipeline::store_pdf_override(kv, semester, &pdf_bytes, &state.pipeline).await?;
+
+    Ok(PdfOverrideResponse {
+        semester,
+        active: true,
+        content_hash: Some(revisions::content_hash(&pdf_bytes)),
+        size_bytes: Some(pdf_bytes.len()),
+    })
+}
+
+async fn admin_pdf_delete_response(
+    req: &Request,
+    state: &AppState,
+    kv: Option<&KvStore>,
+) -> Result<PdfOverrideResponse, ApiError> {
+    authorize_admin(req, state)?;
+    let semester = require_semester_query(&parse_query(req)?)?;
+    csv_pipeline::delete_pdf_override(kv, semester).await?;
+
+    Ok(PdfOverrideResponse {
+        semester,
+        active: false,
+        content_hash: None,
+        size_bytes: None,
+    })
+}
+
+async fn admin_events_patch_response(
+    req: &mut Request,
+    state: &AppState,
+    kv: Option<&KvStore>,
+) -> Result<CorrectionsResponse, ApiError> {
+    authorize_admin(req, state)?;
+    let semester = require_semester_query(&parse_query(req)?)?;
+    let body = req.text().await?;
+    let patch = serde_json::from_str::<CorrectionsPatchRequest>(&body)
+        .map_err(|error| ApiError::BadRequest(format!("invalid corrections body: {error}")))?;
+    let items = corrections::patch_corrections(kv, semester, patch.corrections).await?;
+
+    Ok(CorrectionsResponse { semester, items })
+}
+
+async fn status_response(
+    state: &AppState,
+    kv: Option<&KvStore>,
+) -> Result<StatusResponse, ApiError> {
+    let upstream = load_upstream_status(state).await?;
+    let upstream_cached_at = cache::cached_at(UPSTREAM_STATUS_CACHE_KEY).await?;
+    let links_cached_at = cache::cached_at(LINKS_CACHE_KEY).await?;
+    let links = match load_links(state, kv).await {
+        Ok((links, _)) => links,
+        Err(error) => {
+            worker::console_error!("status could not load semester links: {}", error);
+            Vec::new()
+        }
+    };
+
+    let mut semesters = Vec::with_capacity(links.len());
+    for link in &links {
+        semesters.push(csv_pipeline::semester_status(kv, link.semester).await?);
+    }
+    let canaries = csv_pipeline::list_canaries(kv).await?;
+    let now = Utc::now();
+
+    Ok(StatusResponse {
+        status: service_health(&upstream, !links.is_empty(), &semesters, canaries.len()),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        checked_at: now.to_rfc3339(),
+        upstream,
+        upstream_cache_age_seconds: upstream_cached_at
+            .map(|cached_at| (now - cached_at).num_seconds()),
+        links_cache_age_seconds: links_cached_at.map(|cached_at| (now - cached_at).num_seconds()),
+        semesters,
+        canaries,
+    })
+}
+
+#[must_use]
+pub fn service_health(
+    upstream: &UpstreamStatus,
+    has_links: bool,
+    semesters: &[SemesterStatus],
+    canary_count: usize,
+) -> ServiceHealth {
+    if !has_links {
+        return ServiceHealth::Down;
+    }
+    if !upstream.reachable
+        || canary_count > 0
+        || semesters
+            .iter()
+            .any(|semester| semester.last_failure.is_some())
+    {
+        return ServiceHealth::Degraded;
+    }
+    ServiceHealth::Ok
+}
+
+async fn admin_canary_promote_response(
+    req: &Request,
+    state: &AppState,
+    kv: Option<&KvStore>,
+) -> Result<CanaryRecord, ApiError> {
+    authorize_admin(req, state)?;
+    let semester = require_semester_query(&parse_query(req)?)?;
+    csv_pipeline::promote_canary(kv, semester).await
+}
+
+fn authorize_admin(req: &Request, state: &AppState) -> Result<(), ApiError> {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return Err(ApiError::Unauthorized(
+            "admin endpoints are disabled".to_string(),
+        ));
+    };
+
+    let provided = req.headers().get("Authorization")?;
+    if bearer_token_matches(provided.as_deref(), expected) {
+        Ok(())
+    } else {
+        Err(ApiError::Unauthorized(
+            "missing or invalid admin token".to_string(),
+        ))
+    }
+}
+
+pub fn bearer_token_matches(authorization: Option<&str>, expected: &str) -> bool {
+    let Some(provided) = authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+    else {
+        return false;
+    };
+
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0_u8, |diff, (left, right)| diff | (left ^ right))
+            == 0
+}
+
+async fn load_links(
+    state: &AppState,
+    kv: Option<&KvStore>,
+) -> Result<(Vec<SemesterLink>, bool), ApiError> {
     if let Some(cached) = cache::get_json::<Vec<SemesterLink>>(LINKS_CACHE_KEY).await? {
         if cached.is_empty() {
             return Err(ApiError::NotFound(
@@ -143,17 +608,38 @@ async fn load_links(source_url: &str) -> Result<(Vec<SemesterLink>, bool), ApiEr
         return Ok((cached, true));
     }
 
-    let links = source_scraper::fetch_semester_links(source_url).await?;
+    let mut links =
+        source_scraper::fetch_semester_links(&state.pipeline.http_client(), &state.source_url)
+            .await?;
     if links.is_empty() {
         return Err(ApiError::NotFound(
             "no semester PDF links found from source page".to_string(),
         ));
     }
 
+    revisions::annotate_links(kv, &mut links).await?;
     cache::put_json(LINKS_CACHE_KEY, &links, LINKS_CACHE_TTL_SECONDS).await?;
     Ok((links, false))
 }
 
+/// `/api/v1/status` is public, so the source page is probed at most once per
+/// `UPSTREAM_STATUS_CACHE_TTL_SECONDS` rather than on every request.
+async fn load_upstream_status(state: &AppState) -> Result<UpstreamStatus, ApiError> {
+    if let Some(cached) = cache::get_json::<UpstreamStatus>(UPSTREAM_STATUS_CACHE_KEY).await? {
+        return Ok(cached);
+    }
+
+    let upstream =
+        source_scraper::probe_upstream(&state.pipeline.http_client(), &state.source_url).await;
+    cache::put_json(
+        UPSTREAM_STATUS_CACHE_KEY,
+        &upstream,
+        UPSTREAM_STATUS_CACHE_TTL_SECONDS,
+    )
+    .await?;
+    Ok(upstream)
+}
+
 fn json_response<T>(payload: &T) -> Result<Response>
 where
     T: Serialize,
@@ -163,6 +649,41 @@ where
     Ok(response)
 }
 
+fn conditional_json_response<T>(req: &Request, payload: &T, etag: &str) -> Result<Response>
+where
+    T: Serialize,
+{
+    let if_none_match = req.headers().get("If-None-Match")?;
+    let mut response = if if_none_match_matches(if_none_match.as_deref(), etag) {
+        Response::empty()?.with_status(304)
+    } else {
+        Response::from_json(payload)?
+    };
+    response.headers_mut().set("ETag", etag)?;
+    response.headers_mut().set("Cache-Control", "no-cache")?;
+    Ok(response)
+}
+
+/// # Errors
+///
+/// Returns an error when the links cannot be serialized.
+pub fn links_etag(links: &[SemesterLink], target: i32) -> Result<String, ApiError> {
+    let serialized = serde_json::to_vec(links)?;
+    let digest = revisions::content_hash(&[serialized.as_slice(), &target.to_be_bytes()].concat());
+    Ok(format!("W/\"{}\"", &digest[..16]))
+}
+
+#[must_use]
+pub fn if_none_match_matches(header: Option<&str>, etag: &str) -> bool {
+    let Some(header) = header else {
+        return false;
+    };
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    header
+        .split(',')
+        .any(|candidate| candidate.trim() == "*" || opaque(candidate) == opaque(etag))
+}
+
 fn parse_query(req: &Request) -> Result<HashMap<String, String>, ApiError> {
     let url = req.url()?;
     let query = url
@@ -173,20 +694,32 @@ fn parse_query(req: &Request) -> Result<HashMap<String, String>, ApiError> {
 }
 
 fn parse_semester_query(query: &HashMap<String, String>) -> Result<Option<i32>, ApiError> {
-    let Some(raw) = query.get("semester") else {
+    parse_semester_param(query, "semester")
+}
+
+fn parse_semester_param(
+    query: &HashMap<String, String>,
+    name: &str,
+) -> Result<Option<i32>, ApiError> {
+    let Some(raw) = query.get(name) else {
         return Ok(None);
     };
 
     let parsed = raw.parse::<i32>()?;
     if !(0..=999).contains(&parsed) {
-        return Err(ApiError::BadRequest(
-            "semester must be within 0..=999".to_string(),
-        ));
+        return Err(ApiError::BadRequest(format!(
+            "{name} must be within 0..=999"
+        )));
     }
 
     Ok(Some(parsed))
 }
 
+fn require_semester_query(query: &HashMap<String, String>) -> Result<i32, ApiError> {
+    parse_semester_query(query)?
+        .ok_or_else(|| ApiError::BadRequest("semester query parameter is required".to_string()))
+}
+
 fn parse_all_query(query: &HashMap<String, String>) -> bool {
     query.get("all").is_some_and(|value| {
         let lowered = value.trim().to_ascii_lowercase();
@@ -201,16 +734,19 @@ fn parse_force_query(query: &HashMap<String, String>) -> bool {
     })
 }
 
+#[must_use]
 pub fn roc_year_from_utc(now: DateTime<Utc>) -> i32 {
     let (roc_year, _) = roc_year_and_target_from_utc(now);
     roc_year
 }
 
+#[must_use]
 pub fn target_semester_from_utc(now: DateTime<Utc>) -> i32 {
     let (_, target) = roc_year_and_target_from_utc(now);
     target
 }
 
+#[must_use]
 pub fn roc_year_and_target_from_utc(now: DateTime<Utc>) -> (i32, i32) {
     let taipei_now = now + Duration::hours(8);
     let roc_year = taipei_now.year() - 1911;
@@ -230,6 +766,7 @@ fn current_roc_year_and_target_now() -> (i32, i32) {
     roc_year_and_target_from_utc(Utc::now())
 }
 
+#[must_use]
 pub fn resolve_current_semester(target: i32, links: &[SemesterLink]) -> i32 {
     if links.iter().any(|link| link.semester == target) {
         target
@@ -238,6 +775,9 @@ pub fn resolve_current_semester(target: i32, links: &[SemesterLink]) -> i32 {
     }
 }
 
+/// # Errors
+///
+/// Returns `ApiError::NotFound` when `links` is empty.
 pub fn latest_semester(links: &[SemesterLink]) -> Result<i32, ApiError> {
     links
         .first()
@@ -245,6 +785,9 @@ pub fn latest_semester(links: &[SemesterLink]) -> Result<i32, ApiError> {
         .ok_or_else(|| ApiError::NotFound("no semester links available".to_string()))
 }
 
+/// # Errors
+///
+/// Returns `ApiError::NotFound` when `links` is empty.
 pub fn resolve_selected_semester(
     explicit_semester: Option<i32>,
     links: &[SemesterLink],
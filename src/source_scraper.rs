@@ -1,26 +1,47 @@
 use std::collections::HashSet;
 
+use chrono::Utc;
 use regex::Regex;
 use url::Url;
-use worker::Fetch;
 
 use crate::error::ApiError;
-use crate::models::SemesterLink;
+use crate::http_client::HttpClient;
+use crate::models::{SemesterLink, UpstreamStatus};
 
-pub async fn fetch_semester_links(source_url: &str) -> Result<Vec<SemesterLink>, ApiError> {
-    let source = Url::parse(source_url)?;
-    let mut response = Fetch::Url(source).send().await?;
-    let status = response.status_code();
+pub async fn fetch_semester_links(
+    client: &impl HttpClient,
+    source_url: &str,
+) -> Result<Vec<SemesterLink>, ApiError> {
+    let response = client.get(source_url).await?;
+    let status = response.status;
     if status >= 400 {
         return Err(ApiError::Upstream(format!(
             "failed to fetch source page: status {status}"
         )));
     }
 
-    let html = response.text().await?;
+    let html = response.text()?;
     extract_semester_links(&html, source_url)
 }
 
+pub async fn probe_upstream(client: &impl HttpClient, source_url: &str) -> UpstreamStatus {
+    let started = Utc::now();
+    match client.get(source_url).await {
+        Ok(response) => UpstreamStatus {
+            reachable: response.status < 400,
+            status: Some(response.status),
+            latency_ms: Some((Utc::now() - started).num_milliseconds()),
+            error: None,
+        },
+        Err(error) => UpstreamStatus {
+            reachable: false,
+            status: None,
+            latency_ms: None,
+            error: Some(error.to_string()),
+        },
+    }
+}
+
 pub fn extract_semester_links(html: &str, source_url: &str) -> Result<Vec<SemesterLink>, ApiError> {
     let base_url = Url::parse(source_url)?;
     let anchor_re = Regex::new(
@@ -61,6 +82,8 @@ pub fn extract_semester_links(html: &str, source_url: &str) -> Result<Vec<Semest
                 semester,
                 url: joined_url.to_string(),
                 title: clean_text,
+                revision: 0,
+                content_hash: None,
             });
         }
     }
@@ -0,0 +1,33 @@
+use std::fmt::Write;
+
+pub const FAVICON_ICO: &[u8] = include_bytes!("../assets/favicon.ico");
+pub const STATIC_CACHE_SECONDS: u32 = 7 * 24 * 60 * 60;
+
+/// Only cheap endpoints go in the sitemap so crawlers never trigger PDF
+/// fetches or calendar builds.
+pub const PUBLIC_ENDPOINTS: &[&str] = &["/api/v1/current_semester", "/api/v1/cal_link"];
+
+pub const DISALLOWED_PATHS: &[&str] = &["/api/v1/admin/"];
+
+#[must_use]
+pub fn robots_txt(origin: &str) -> String {
+    let mut body = "User-agent: *\n".to_string();
+    for path in DISALLOWED_PATHS {
+        let _ = writeln!(body, "Disallow: {path}");
+    }
+    let _ = writeln!(body, "\nSitemap: {origin}/sitemap.xml");
+    body
+}
+
+#[must_use]
+pub fn sitemap_xml(origin: &str) -> String {
+    let mut body = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for path in PUBLIC_ENDPOINTS {
+        let _ = writeln!(body, "  <url><loc>{origin}{path}</loc></url>");
+    }
+    body.push_str("</urlset>\n");
+    body
+}
@@ -0,0 +1,152 @@
+use std::io::Write;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use crate::cache;
+use crate::csv_pipeline::CSV_CACHE_TTL_SECONDS;
+use crate::error::ApiError;
+use crate::revisions::content_hash;
+
+pub const VARY_HEADER_VALUE: &str = "Accept, Accept-Encoding";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Csv,
+    Ics,
+}
+
+impl OutputFormat {
+    #[must_use]
+    pub const fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Ics => "text/calendar; charset=utf-8",
+        }
+    }
+
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ics => "ics",
+        }
+    }
+
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "text/csv" => Some(Self::Csv),
+            "text/calendar" => Some(Self::Ics),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Identity,
+    Gzip,
+}
+
+impl ContentEncoding {
+    #[must_use]
+    pub const fn as_header_value(self) -> &'static str {
+        match self {
+            Self::Identity => "identity",
+            Self::Gzip => "gzip",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Variant {
+    pub format: OutputFormat,
+    pub encoding: ContentEncoding,
+}
+
+impl Variant {
+    pub fn negotiate(
+        accept: Option<&str>,
+        accept_encoding: Option<&str>,
+        default_format: OutputFormat,
+    ) -> Self {
+        let format = accept
+            .into_iter()
+            .flat_map(accepted_tokens)
+            .find_map(|media_type| OutputFormat::from_media_type(&media_type))
+            .unwrap_or(default_format);
+        let encoding = if accept_encoding
+            .into_iter()
+            .flat_map(accepted_tokens)
+            .any(|coding| coding == "gzip")
+        {
+            ContentEncoding::Gzip
+        } else {
+            ContentEncoding::Identity
+        };
+
+        Self { format, encoding }
+    }
+
+    #[must_use]
+    pub fn cache_key(self, base_key: &str) -> String {
+        format!(
+            "{base_key}:{}:{}",
+            self.format.extension(),
+            self.encoding.as_header_value()
+        )
+    }
+}
+
+fn accepted_tokens(header: &str) -> impl Iterator<Item = String> + '_ {
+    header.split(',').filter_map(|part| {
+        let mut pieces = part.split(';').map(str::trim);
+        let token = pieces.next()?.to_ascii_lowercase();
+        let rejected = pieces.any(|param| {
+            param
+                .strip_prefix("q=")
+                .and_then(|quality| quality.parse::<f32>().ok())
+                .is_some_and(|quality| quality <= 0.0)
+        });
+        (!token.is_empty() && !rejected).then_some(token)
+    })
+}
+
+/// # Errors
+///
+/// Returns an error when gzip encoding or the cache access fails.
+pub async fn encoded_body(
+    base_key: &str,
+    variant: Variant,
+    body: Vec<u8>,
+) -> Result<Vec<u8>, ApiError> {
+    if variant.encoding == ContentEncoding::Identity {
+        return Ok(body);
+    }
+
+    let key = variant.cache_key(&format!("{base_key}:{}", &content_hash(&body)[..16]));
+    if let Some(cached) = cache::get_bytes(&key).await? {
+        return Ok(cached);
+    }
+
+    let compressed = gzip(&body)?;
+    cache::put_bytes(
+        &key,
+        &compressed,
+        CSV_CACHE_TTL_SECONDS,
+        variant.format.content_type(),
+    )
+    .await?;
+    Ok(compressed)
+}
+
+/// # Errors
+///
+/// Returns `ApiError::Internal` when the encoder fails.
+pub fn gzip(body: &[u8]) -> Result<Vec<u8>, ApiError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(body)
+        .and_then(|()| encoder.finish())
+        .map_err(|error| ApiError::Internal(format!("failed to gzip response: {error}")))
+}
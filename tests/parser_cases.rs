@@ -1,10 +1,33 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
 use chrono::{DateTime, Utc};
 
-use chihlee_cal_worker::models::{ResolvedBy, SemesterLink};
+use chihlee_cal_worker::compare::compare_calendars;
+use chihlee_cal_worker::corrections::{apply_corrections, merge_corrections};
+use chihlee_cal_worker::csv_pipeline::{
+    PipelineConfig, exceeds_canary_threshold, fetch_pdf_bytes, next_sync_index,
+    parse_backfill_semesters, parse_calendar_noise, validate_extraction, with_deadline,
+};
+use chihlee_cal_worker::error::ApiError;
+use chihlee_cal_worker::fixtures::{self, parse_dev_fixtures};
+use chihlee_cal_worker::http_client::{FixtureClient, HttpClient, HttpResponse};
+use chihlee_cal_worker::ics::{IcsUidRegistry, assign_uids, parse_event_dates, render_ics};
+use chihlee_cal_worker::kv_store;
+use chihlee_cal_worker::models::{
+    CorrectionAction, EventCorrection, ExtractionFailure, PdfRevision, ResolvedBy, SemesterLink,
+    SemesterStatus, ServiceHealth, UpstreamStatus,
+};
+use chihlee_cal_worker::revisions::next_revision;
 use chihlee_cal_worker::routes::{
-    resolve_current_semester, resolve_selected_semester, roc_year_from_utc, target_semester_from_utc,
+    bearer_token_matches, if_none_match_matches, links_etag, resolve_current_semester,
+    resolve_selected_semester, roc_year_from_utc, service_health, target_semester_from_utc,
+};
+use chihlee_cal_worker::source_scraper::{
+    extract_semester, extract_semester_links, fetch_semester_links,
 };
-use chihlee_cal_worker::source_scraper::{extract_semester, extract_semester_links};
+use chihlee_cal_worker::static_assets::{self, robots_txt, sitemap_xml};
+use chihlee_cal_worker::variants::{ContentEncoding, OutputFormat, Variant};
 
 fn sample_links() -> Vec<SemesterLink> {
     vec![
@@ -12,16 +35,22 @@ fn sample_links() -> Vec<SemesterLink> {
             semester: 115,
             url: "https://example.com/115.pdf".to_string(),
             title: "115".to_string(),
+            revision: 0,
+            content_hash: None,
         },
         SemesterLink {
             semester: 114,
             url: "https://example.com/114.pdf".to_string(),
             title: "114".to_string(),
+            revision: 0,
+            content_hash: None,
         },
         SemesterLink {
             semester: 113,
             url: "https://example.com/113.pdf".to_string(),
             title: "113".to_string(),
+            revision: 0,
+            content_hash: None,
         },
     ]
 }
@@ -88,3 +117,556 @@ fn cal_link_selection_precedence_and_default_fallback() {
     assert_eq!(latest.semester, 115);
     assert_eq!(latest.resolved_by, ResolvedBy::Latest);
 }
+
+#[test]
+fn sync_cursor_round_robins_over_links() {
+    assert_eq!(next_sync_index(0, 3), 0);
+    assert_eq!(next_sync_index(2, 3), 2);
+    assert_eq!(next_sync_index(3, 3), 0);
+    assert_eq!(next_sync_index(7, 0), 0);
+}
+
+#[test]
+fn missing_kv_binding_is_rejected_where_required() {
+    let error = kv_store::require(None).expect_err("an unbound namespace should be rejected");
+    assert!(matches!(error, ApiError::Internal(_)));
+    assert!(error.message().contains("CAL_KV"));
+}
+
+#[test]
+fn backfill_semesters_ignores_zero_and_garbage() {
+    assert_eq!(parse_backfill_semesters(" 3 "), Some(3));
+    assert_eq!(parse_backfill_semesters("0"), None);
+    assert_eq!(parse_backfill_semesters("all"), None);
+}
+
+#[test]
+fn calendar_noise_splits_on_commas() {
+    assert_eq!(
+        parse_calendar_noise(" 本表經行政會議通過 ,,校長室 "),
+        vec!["本表經行政會議通過".to_string(), "校長室".to_string()]
+    );
+    assert!(parse_calendar_noise(" , ").is_empty());
+}
+
+#[test]
+fn revision_only_advances_when_content_hash_changes() {
+    let first = next_revision(
+        &[],
+        "aaa",
+        "https://example.com/114.pdf",
+        "2026-01-01T00:00:00Z",
+    )
+    .expect("first observation is a revision");
+    assert_eq!(first.revision, 1);
+
+    let history: Vec<PdfRevision> = vec![first];
+    assert!(
+        next_revision(
+            &history,
+            "aaa",
+            "https://example.com/114.pdf",
+            "2026-02-01T00:00:00Z"
+        )
+        .is_none()
+    );
+
+    let replaced = next_revision(
+        &history,
+        "bbb",
+        "https://example.com/114.pdf",
+        "2026-03-01T00:00:00Z",
+    )
+    .expect("changed hash is a new revision");
+    assert_eq!(replaced.revision, 2);
+}
+
+#[test]
+fn admin_token_requires_exact_bearer_match() {
+    assert!(bearer_token_matches(Some("Bearer s3cret"), "s3cret"));
+    assert!(!bearer_token_matches(Some("Bearer s3cre"), "s3cret"));
+    assert!(!bearer_token_matches(Some("s3cret"), "s3cret"));
+    assert!(!bearer_token_matches(None, "s3cret"));
+}
+
+#[test]
+fn links_etag_is_weak_and_tracks_links_and_target() {
+    let links = sample_links();
+    let etag = links_etag(&links, 114).expect("etag should serialize");
+    assert!(etag.starts_with("W/\""));
+    assert_eq!(etag, links_etag(&links, 114).unwrap());
+    assert_ne!(etag, links_etag(&links, 115).unwrap());
+    assert_ne!(etag, links_etag(&links[1..], 114).unwrap());
+
+    assert!(if_none_match_matches(Some(&etag), &etag));
+    assert!(if_none_match_matches(
+        Some(&format!("\"other\", {}", etag.trim_start_matches("W/"))),
+        &etag
+    ));
+    assert!(if_none_match_matches(Some("*"), &etag));
+    assert!(!if_none_match_matches(Some("W/\"other\""), &etag));
+    assert!(!if_none_match_matches(None, &etag));
+}
+
+fn correction(date: &str, event: &str, action: CorrectionAction) -> EventCorrection {
+    EventCorrection {
+        date: date.to_string(),
+        event: event.to_string(),
+        action,
+        new_date: None,
+        new_event: None,
+    }
+}
+
+#[test]
+fn corrections_overlay_replaces_and_suppresses_rows() {
+    let csv = "date,event\n9/2,開學\n9/9,\"轉學生入學輔導, 新生\"\n10/10,國慶日\n";
+    let mut replace = correction("9/2", "開學", CorrectionAction::Replace);
+    replace.new_date = Some("9/1".to_string());
+    let overlay = vec![
+        replace,
+        correction("10/10", "國慶日", CorrectionAction::Suppress),
+    ];
+
+    let corrected = apply_corrections(csv, &overlay).expect("overlay applies");
+    assert_eq!(
+        corrected,
+        "date,event\n9/1,開學\n9/9,\"轉學生入學輔導, 新生\"\n"
+    );
+}
+
+#[test]
+fn corrections_patch_upserts_and_clears_by_date_and_event() {
+    let existing = vec![correction("9/2", "開學", CorrectionAction::Suppress)];
+    let merged = merge_corrections(
+        existing,
+        vec![
+            correction("9/2", "開學", CorrectionAction::Clear),
+            correction("9/9", "轉學生入學輔導", CorrectionAction::Suppress),
+        ],
+    );
+
+    assert_eq!(
+        merged,
+        vec![correction(
+            "9/9",
+            "轉學生入學輔導",
+            CorrectionAction::Suppress
+        )]
+    );
+}
+
+fn calendar_csv(months: &[u32], rows_per_month: u32) -> String {
+    let mut csv = "date,event\n".to_string();
+    for month in months {
+        for day in 1..=rows_per_month {
+            let _ = writeln!(csv, "{month}/{day},事件{day}");
+        }
+    }
+    csv
+}
+
+#[test]
+fn extraction_gate_rejects_degraded_output() {
+    let config = PipelineConfig::default();
+    let healthy = calendar_csv(&[8, 9, 10, 11, 12, 1, 2], 4);
+    assert!(validate_extraction(114, &healthy, 0, &config).is_ok());
+
+    assert!(validate_extraction(114, &healthy, 100, &config).is_err());
+    assert!(
+        validate_extraction(114, &calendar_csv(&[9, 10, 11, 12, 1, 2], 2), 0, &config).is_err()
+    );
+    assert!(validate_extraction(114, &calendar_csv(&[9, 10], 20), 0, &config).is_err());
+}
+
+#[test]
+fn year_prefixed_rows_resolve_for_ics_and_the_extraction_gate() {
+    let (start, end) = parse_event_dates("2025/9/15", 114).expect("year-prefixed date parses");
+    assert_eq!(
+        (start.to_string(), end.to_string()),
+        ("2025-09-15".into(), "2025-09-15".into())
+    );
+    let (start, end) =
+        parse_event_dates("2025/12/29~2026/1/2", 114).expect("year-prefixed range parses");
+    assert_eq!(start.to_string(), "2025-12-29");
+    assert_eq!(end.to_string(), "2026-01-02");
+
+    let mut registry = IcsUidRegistry::new();
+    let rows = vec![
+        ("9/1".to_string(), "開學".to_string()),
+        ("2025/9/22~2025/9/26".to_string(), "補考週".to_string()),
+    ];
+    let (events, _) = assign_uids(&mut registry, 114, &rows);
+    assert_eq!(events.len(), 2);
+
+    let rows = [8, 9, 10, 11, 12, 1]
+        .into_iter()
+        .flat_map(|month| (1..=4).map(move |day| (month, day)))
+        .map(|(month, day)| {
+            let year = if month >= 8 { 2025 } else { 2026 };
+            format!("{year}/{month}/{day},事件{day}")
+        })
+        .collect::<Vec<_>>();
+    let csv = format!("date,event\n{}\n", rows.join("\n"));
+    assert!(validate_extraction(114, &csv, 0, &PipelineConfig::default()).is_ok());
+}
+
+#[test]
+fn extraction_gate_uses_configured_thresholds() {
+    let short_term = calendar_csv(&[9, 10], 5);
+    let relaxed = PipelineConfig {
+        min_extracted_rows: 10,
+        min_distinct_months: 2,
+        max_extraction_warnings: 0,
+        ..PipelineConfig::default()
+    };
+    assert!(validate_extraction(114, &short_term, 0, &relaxed).is_ok());
+
+    let error =
+        validate_extraction(114, &short_term, 1, &relaxed).expect_err("one warning is too many");
+    assert_eq!(error.message(), "extraction produced 1 warnings (max 0)");
+}
+
+#[test]
+fn canary_threshold_compares_row_delta_to_cached_rows() {
+    assert!(!exceeds_canary_threshold(100, 120, 20));
+    assert!(exceeds_canary_threshold(100, 121, 20));
+    assert!(exceeds_canary_threshold(100, 79, 20));
+    assert!(!exceeds_canary_threshold(0, 50, 20));
+    assert!(!exceeds_canary_threshold(100, 10, 0));
+}
+
+#[test]
+fn event_dates_follow_academic_year_rollover() {
+    let (start, end) = parse_event_dates("12/29~1/2", 114).expect("range parses");
+    assert_eq!(start.to_string(), "2025-12-29");
+    assert_eq!(end.to_string(), "2026-01-02");
+
+    let (start, end) = parse_event_dates("12/8起", 114).expect("open start parses");
+    assert_eq!(
+        (start.to_string(), end.to_string()),
+        ("2025-12-08".into(), "2025-12-08".into())
+    );
+    assert!(parse_event_dates("備註", 114).is_none());
+}
+
+#[test]
+fn ics_uids_stay_stable_and_bump_sequence_on_text_change() {
+    let mut registry = IcsUidRegistry::new();
+    let original = vec![
+        ("9/2".to_string(), "開學".to_string()),
+        ("9/2".to_string(), "導師知能研習".to_string()),
+    ];
+    let (first, changed) = assign_uids(&mut registry, 114, &original);
+    assert!(changed);
+    assert_ne!(first[0].uid, first[1].uid);
+
+    let (unchanged, changed) = assign_uids(&mut registry, 114, &original);
+    assert!(!changed);
+    assert_eq!(unchanged, first);
+
+    let edited = vec![
+        ("9/2".to_string(), "開學典禮".to_string()),
+        ("9/2".to_string(), "導師知能研習".to_string()),
+    ];
+    let (updated, changed) = assign_uids(&mut registry, 114, &edited);
+    assert!(changed);
+    assert_eq!(updated[0].uid, first[0].uid);
+    assert_eq!(updated[0].sequence, 1);
+    assert_eq!(updated[1].sequence, 0);
+}
+
+#[test]
+fn ics_uids_follow_event_text_across_reorders_and_moves() {
+    let mut registry = IcsUidRegistry::new();
+    let original = vec![
+        ("9/2".to_string(), "開學".to_string()),
+        ("9/2".to_string(), "導師知能研習".to_string()),
+        ("10/10".to_string(), "國慶日放假".to_string()),
+    ];
+    let (first, _) = assign_uids(&mut registry, 114, &original);
+
+    let reordered = vec![
+        ("9/1".to_string(), "新生訓練".to_string()),
+        ("9/2".to_string(), "導師知能研習".to_string()),
+        ("9/3".to_string(), "開 學".to_string()),
+    ];
+    let (updated, changed) = assign_uids(&mut registry, 114, &reordered);
+    assert!(changed);
+    assert!(first.iter().all(|event| event.uid != updated[0].uid));
+    assert_eq!(updated[1].uid, first[1].uid);
+    assert_eq!(updated[1].sequence, 0);
+    assert_eq!(updated[2].uid, first[0].uid);
+    assert_eq!(updated[2].sequence, 1);
+    assert_eq!(registry.len(), 3);
+    assert!(!registry.contains_key(&first[2].uid));
+}
+
+#[test]
+fn ics_body_is_stamped_with_the_csv_build_time() {
+    let mut registry = IcsUidRegistry::new();
+    let rows = vec![("9/2".to_string(), "開學".to_string())];
+    let (events, _) = assign_uids(&mut registry, 114, &rows);
+    let built_at = DateTime::parse_from_rfc3339("2026-01-11T02:00:04Z")
+        .expect("timestamp parses")
+        .with_timezone(&Utc);
+
+    let body = render_ics(114, &events, built_at);
+    assert!(body.contains("DTSTAMP:20260111T020004Z\r\n"));
+    assert_eq!(render_ics(114, &events, built_at), body);
+}
+
+#[test]
+fn variant_negotiation_uses_distinct_cache_keys() {
+    let csv = Variant::negotiate(None, Some("br, gzip;q=0"), OutputFormat::Csv);
+    assert_eq!(csv.format, OutputFormat::Csv);
+    assert_eq!(csv.encoding, ContentEncoding::Identity);
+
+    let ics = Variant::negotiate(
+        Some("text/calendar, text/csv;q=0.5"),
+        Some("gzip, deflate"),
+        OutputFormat::Csv,
+    );
+    assert_eq!(ics.format, OutputFormat::Ics);
+    assert_eq!(ics.encoding, ContentEncoding::Gzip);
+
+    assert_ne!(
+        csv.cache_key("csv:semester:v1:114"),
+        ics.cache_key("csv:semester:v1:114")
+    );
+}
+
+#[test]
+fn dev_fixtures_flag_and_bundled_source_page() {
+    assert!(parse_dev_fixtures("true"));
+    assert!(parse_dev_fixtures(" YES "));
+    assert!(!parse_dev_fixtures("false"));
+    assert!(!parse_dev_fixtures(""));
+
+    let links = extract_semester_links(fixtures::SOURCE_HTML, "http://127.0.0.1:8787/")
+        .expect("fixture page should parse");
+    let semesters = links.iter().map(|link| link.semester).collect::<Vec<_>>();
+    assert_eq!(semesters, vec![114, 113]);
+}
+
+#[test]
+fn bundled_calendar_pdf_passes_extraction_gate() {
+    let (csv, report) = chihlee_cal_to_csv::extract_pdf_bytes_to_csv_string(
+        fixtures::CALENDAR_PDF,
+        &calendar_extract_options(),
+    )
+    .expect("fixture PDF should convert");
+
+    assert!(csv.starts_with("category,date,event"));
+    assert!(
+        validate_extraction(114, &csv, report.warnings.len(), &PipelineConfig::default()).is_ok()
+    );
+}
+
+#[test]
+fn passed_deadline_interrupts_extraction() {
+    let options = with_deadline(
+        calendar_extract_options(),
+        Utc::now() - chrono::Duration::seconds(1),
+    );
+    let result =
+        chihlee_cal_to_csv::extract_pdf_bytes_to_csv_string(fixtures::CALENDAR_PDF, &options);
+
+    assert!(matches!(
+        result,
+        Err(chihlee_cal_to_csv::ExtractError::Cancelled)
+    ));
+}
+
+#[test]
+fn future_deadline_lets_extraction_finish() {
+    let options = with_deadline(
+        calendar_extract_options(),
+        Utc::now() + chrono::Duration::minutes(5),
+    );
+
+    assert!(
+        chihlee_cal_to_csv::extract_pdf_bytes_to_csv_string(fixtures::CALENDAR_PDF, &options)
+            .is_ok()
+    );
+}
+
+fn calendar_extract_options() -> chihlee_cal_to_csv::ExtractOptions {
+    chihlee_cal_to_csv::ExtractOptions {
+        clean_calendar: true,
+        no_page: true,
+        no_table: true,
+        rename_columns: BTreeMap::from([
+            ("col_1".to_string(), "date".to_string()),
+            ("col_2".to_string(), "event".to_string()),
+        ]),
+        calendar_category_column: true,
+        ..chihlee_cal_to_csv::ExtractOptions::default()
+    }
+}
+
+struct CannedClient {
+    status: u16,
+    body: &'static [u8],
+}
+
+impl HttpClient for CannedClient {
+    async fn get(&self, _url: &str) -> Result<HttpResponse, ApiError> {
+        Ok(HttpResponse {
+            status: self.status,
+            body: self.body.to_vec(),
+        })
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+    loop {
+        if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn fetchers_use_injected_http_client() {
+    let source_url = "https://www.chihlee.edu.tw/p/404-1000-62149.php";
+    let page = CannedClient {
+        status: 200,
+        body: r#"<a href="/files/114.pdf">114學年度行事曆</a>"#.as_bytes(),
+    };
+    let links = block_on(fetch_semester_links(&page, source_url)).expect("links should parse");
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].semester, 114);
+
+    let missing = CannedClient {
+        status: 404,
+        body: b"",
+    };
+    assert!(matches!(
+        block_on(fetch_semester_links(&missing, source_url)),
+        Err(ApiError::Upstream(_))
+    ));
+    assert!(matches!(
+        block_on(fetch_pdf_bytes(
+            &CannedClient {
+                status: 200,
+                body: b""
+            },
+            source_url
+        )),
+        Err(ApiError::Upstream(_))
+    ));
+
+    let pdf = block_on(fetch_pdf_bytes(
+        &FixtureClient,
+        "http://127.0.0.1/fixtures/114.pdf",
+    ))
+    .expect("fixture PDF should be served");
+    assert_eq!(pdf, fixtures::CALENDAR_PDF);
+}
+
+fn rows(items: &[(&str, &str)]) -> Vec<(String, String)> {
+    items
+        .iter()
+        .map(|(date, event)| ((*date).to_string(), (*event).to_string()))
+        .collect()
+}
+
+#[test]
+fn compare_aligns_similar_events_across_semesters() {
+    let from = rows(&[
+        ("9/9", "113學年度開學典禮"),
+        ("11/4~11/8", "期中考試週"),
+        ("12/25", "行憲紀念日"),
+    ]);
+    let to = rows(&[
+        ("9/8", "114學年度開學典禮"),
+        ("11/10~11/14", "期中考試週"),
+        ("10/1", "新生健康檢查"),
+    ]);
+
+    let report = compare_calendars(113, &from, 114, &to);
+    assert_eq!(report.unchanged_count, 0);
+    assert_eq!(report.shifted.len(), 2);
+    assert_eq!(report.shifted[0].from_date, "9/9");
+    assert_eq!(report.shifted[0].to_date, "9/8");
+    assert_eq!(report.shifted[0].shift_days, Some(-1));
+    assert_eq!(report.shifted[1].shift_days, Some(6));
+    assert_eq!(report.added.len(), 1);
+    assert_eq!(report.added[0].event, "新生健康檢查");
+    assert_eq!(report.removed.len(), 1);
+    assert_eq!(report.removed[0].event, "行憲紀念日");
+}
+
+#[test]
+fn service_health_reflects_upstream_failures_and_canaries() {
+    let reachable = UpstreamStatus {
+        reachable: true,
+        status: Some(200),
+        latency_ms: Some(120),
+        error: None,
+    };
+    let unreachable = UpstreamStatus {
+        reachable: false,
+        status: None,
+        latency_ms: None,
+        error: Some("upstream_error: timeout".to_string()),
+    };
+    let healthy = SemesterStatus {
+        semester: 114,
+        last_success: None,
+        last_failure: None,
+        csv_cache_age_seconds: Some(3_600),
+    };
+    let failing = SemesterStatus {
+        last_failure: Some(ExtractionFailure {
+            semester: 114,
+            reason: "too few rows".to_string(),
+            observed_at: "2026-01-11T02:00:04+00:00".to_string(),
+            degraded_in_cache: false,
+        }),
+        ..healthy.clone()
+    };
+
+    let semesters = std::slice::from_ref(&healthy);
+    assert_eq!(
+        service_health(&reachable, true, semesters, 0),
+        ServiceHealth::Ok
+    );
+    assert_eq!(
+        service_health(&unreachable, true, semesters, 0),
+        ServiceHealth::Degraded
+    );
+    assert_eq!(
+        service_health(&reachable, true, semesters, 1),
+        ServiceHealth::Degraded
+    );
+    assert_eq!(
+        service_health(&reachable, true, &[failing], 0),
+        ServiceHealth::Degraded
+    );
+    assert_eq!(
+        service_health(&unreachable, false, &[], 0),
+        ServiceHealth::Down
+    );
+}
+
+#[test]
+fn robots_and_sitemap_cover_public_and_admin_paths() {
+    let robots = robots_txt("https://cal.example.dev");
+    assert!(robots.contains("Disallow: /api/v1/admin/"));
+    assert!(!robots.contains("/debug/"));
+    assert!(robots.contains("Sitemap: https://cal.example.dev/sitemap.xml"));
+
+    let sitemap = sitemap_xml("https://cal.example.dev");
+    for path in static_assets::PUBLIC_ENDPOINTS {
+        assert!(sitemap.contains(&format!("<loc>https://cal.example.dev{path}</loc>")));
+    }
+    assert!(!sitemap.contains("/admin/"));
+    for expensive in ["/api/v1/csv", "/api/v1/ics", "/api/v1/status"] {
+        assert!(!sitemap.contains(&format!("{expensive}<")));
+    }
+    assert!(static_assets::FAVICON_ICO.starts_with(&[0, 0, 1, 0]));
+}